@@ -103,6 +103,11 @@ resim show $customer
 echo ' > nft_manager'
 resim show $nft_manager
 
+# Accept XRD as a currency for the offers
+export currency=$xrd
+echo '>>> Add XRD as an accepted currency'
+resim run fcgsales/add_currency.rtm
+
 # Mint and send a badge to a Customer (to enable it to accept/refuse offers) 
 export customer_name=Azienda1
 echo '>>> Mint a Customer Badge'
@@ -127,13 +132,30 @@ export manager_badge_id=$(echo "$customer_fungible" | awk '/Fcgsales Manager Bad
 # Display the extracted value
 echo 'Manager Badge = ' $manager_badge_id
 
+# Get the Customer Badge LocalId, the offer will be bound to this badge
+customer_fungible=$(resim show $customer_account)
+# Extract the value inside the braces after "Fcgsales Customer Badge (Fcgsales Customer)"
+export customer_badge_id=$(echo "$customer_fungible" | awk '/Fcgsales Customer Badge/ {getline; print $2}' | tr -d '{}')
+# Display the extracted value
+echo 'Customer Badge = ' $customer_badge_id
+
 # Send an Offer to a Customer
 # TODO - The offer is being sent as an ADMIN
 # TODO - Here it is needed to change Default account to a Manager
 # TODO - Here it is needed to fetch NFT Local ids
 export hash_pdf=hash_pdf
-export expiry_date=100
-export offer_amount=400
+# expiry date in UTC seconds, 30 days from now
+export expiry_date=$(( $(date +%s) + 30*24*3600 ))
+# a single line offer of 400
+export sku=SKU1
+export description_hash=description_hash
+export quantity=1
+export unit_price=400
+export discount=0
+# the payment is not held in escrow
+export escrow=false
+# no deposit is required on acceptance
+export deposit_ratio=0
 export account=$owner_account
 export amount=1
 # echo '>>> Send Offer'
@@ -164,20 +186,15 @@ nft_data=$(echo "$fungible" | awk '/Sales OfferData NFT/ {getline; print $2}' |
 # Display the extracted value
 echo 'Sales OfferData NFT Data = ' $nft_data
 
-# Extract the value inside the braces after "Fcgsales Customer Badge (Fcgsales Customer)"
-export customer_badge_id=$(echo "$fungible" | awk '/Fcgsales Customer Badge/ {getline; print $2}' | tr -d '{}')
-# Display the extracted value
-echo 'Customer Badge = ' $customer_badge_id
-
 
 
 
 
-# # TODO - Cancel the offer
+# # TODO - Cancel the offer (vaultAddress is the customer vault holding the OfferData NFT)
 # export vaultAddress=abc
 # export fungibleId={$nft_data}
-# echo '>>> Accept Offer ' $fungibleId
-# resim run fcgsales/recall_offer.rtm
+# echo '>>> Cancel Offer ' $fungibleId
+# resim run fcgsales/cancel_offer.rtm
 
 # # TODO - Check if the NFT has been recalled
 # echo ' > customer account'
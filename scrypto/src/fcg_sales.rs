@@ -12,20 +12,75 @@
 //! [register()][fcgsales::Fcgsales::send_offer]
 //! Send an Offer to a Customer
 //! 
+//! Every Offer gets a sequential offer number (e.g. OFF-2026-000123), restarting every year,
+//! to find it with [get_offer_by_number()][fcgsales::Fcgsales::get_offer_by_number]
+//! 
+//! An Offer above the approval limit of its currency is kept by the component until an Admin or a senior Manager 
+//! approves it ([approve_offer()][fcgsales::Fcgsales::approve_offer]) or rejects it ([reject_internal()][fcgsales::Fcgsales::reject_internal])
+//! 
 //! ## Cancel Offer
 //!
 //! [register()][fcgsales::Fcgsales::cancel_offer]
-//! Cancel an Offer to a Customer
+//! Cancel an Offer to a Customer, recalling its OfferData NFT
+//! 
+//! ## Amend Offer
+//!
+//! [amend_offer()][fcgsales::Fcgsales::amend_offer]
+//! Change the expiry date or the lines of an Offer, keeping its previous versions
 //! 
 //! ## Accept Offer
 //!
 //! [register()][fcgsales::Fcgsales::accept_offer]
 //! Accept an Offer to a Customer
 //! 
+//! An Offer can require the approval of several Customer Badges (e.g. procurement and CFO): every signer accepts it 
+//! and the Offer is accepted once the approval threshold is reached
+//! 
+//! ## Accept and Pay Offer
+//!
+//! [accept_and_pay()][fcgsales::Fcgsales::accept_and_pay]
+//! Accept an Offer and pay it (or its deposit) in its currency
+//! 
+//! ## Pay Balance
+//!
+//! [pay_balance()][fcgsales::Fcgsales::pay_balance]
+//! Pay the outstanding amount of an Offer accepted with a deposit
+//! 
+//! ## Milestones
+//!
+//! A project offer can carry a payment schedule: the Manager marks each milestone as delivered 
+//! ([mark_milestone_delivered()][fcgsales::Fcgsales::mark_milestone_delivered]) and the Customer pays it
+//! ([pay_milestone()][fcgsales::Fcgsales::pay_milestone]), the Invoice NFT of a milestone is claimed by the Customer 
+//! ([claim_invoices()][fcgsales::Fcgsales::claim_invoices])
+//! 
+//! ## Escrow
+//!
+//! For an escrow offer the payment made with accept_and_pay is held by the component until the Manager marks the offer as 
+//! delivered ([mark_delivered()][fcgsales::Fcgsales::mark_delivered]) and the Customer confirms it 
+//! ([confirm_delivery()][fcgsales::Fcgsales::confirm_delivery]), or the escrow timeout passes 
+//! ([release_escrow()][fcgsales::Fcgsales::release_escrow])
+//! 
 //! ## Refuse Offer
 //!
 //! [register()][fcgsales::Fcgsales::refuse_offer]
-//! Refuse an Offer to a Customer
+//! Refuse an Offer to a Customer, giving a reason ([get_refusal_counts()][fcgsales::Fcgsales::get_refusal_counts] 
+//! counts the refusals by reason)
+//! 
+//! ## Counter Offer
+//!
+//! [counter_offer()][fcgsales::Fcgsales::counter_offer]
+//! Send a counter-proposal for an Offer, to be accepted ([accept_counter()][fcgsales::Fcgsales::accept_counter]) 
+//! or rejected ([reject_counter()][fcgsales::Fcgsales::reject_counter]) by a Manager
+//! 
+//! ## Expire Offers
+//!
+//! [expire_offers()][fcgsales::Fcgsales::expire_offers]
+//! Move the offers past their expiry date to the EXPIRED state
+//! 
+//! ## Invoices
+//!
+//! An Invoice NFT, numbered sequentially, is minted by the component and given to the Customer when an Offer is accepted, 
+//! or when a milestone is delivered ([get_invoices()][fcgsales::Fcgsales::get_invoices] lists those of an Offer)
 //! 
 //! # Overview of secondary functions
 //!
@@ -44,51 +99,544 @@
 //! 
 
 use scrypto::prelude::*;
+use scrypto::engine::scrypto_env::ScryptoVmV1Api;
 use scrypto_avltree::AvlTree;
 
+/// this is to contain the state of an offer
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum OfferState {
+    Draft,
+    PendingApproval,
+    New,
+    Accepted,
+    Refused,
+    Cancelled,
+    Expired,
+    Countered,
+    PartiallyPaid,
+    FullyPaid,
+    Escrowed,
+    Delivered,
+    Superseded,
+}
+
+impl OfferState {
+    /// this is the transition table of an offer, every state change has to be allowed here
+    pub fn can_transition_to(&self, next: OfferState) -> bool {
+        match (self, next) {
+            (OfferState::Draft, OfferState::New) => true,
+            (OfferState::Draft, OfferState::Cancelled) => true,
+            (OfferState::PendingApproval, OfferState::New) => true,
+            (OfferState::PendingApproval, OfferState::Draft) => true,
+            (OfferState::PendingApproval, OfferState::Cancelled) => true,
+            (OfferState::New, OfferState::Accepted) => true,
+            (OfferState::Accepted, OfferState::PartiallyPaid) => true,
+            (OfferState::Accepted, OfferState::FullyPaid) => true,
+            (OfferState::PartiallyPaid, OfferState::FullyPaid) => true,
+            (OfferState::Accepted, OfferState::Escrowed) => true,
+            (OfferState::Escrowed, OfferState::Delivered) => true,
+            // only by releasing the escrow, the funds held by the component go to the treasury
+            (OfferState::Delivered, OfferState::FullyPaid) => true,
+            (OfferState::New, OfferState::Refused) => true,
+            (OfferState::New, OfferState::Cancelled) => true,
+            (OfferState::New, OfferState::Expired) => true,
+            (OfferState::New, OfferState::Countered) => true,
+            (OfferState::Countered, OfferState::New) => true,
+            (OfferState::Countered, OfferState::Cancelled) => true,
+            (OfferState::Countered, OfferState::Superseded) => true,
+            _ => false,
+        }
+    }
+}
+
+/// number of decimal places the amount of an offer line is rounded to
+pub const AMOUNT_DECIMAL_PLACES: u8 = 2;
+
+/// this is to contain the reason given by a customer for refusing an offer
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RefusalReason {
+    Price,
+    Timing,
+    Competitor,
+    Scope,
+    Other,
+}
+
+impl RefusalReason {
+    /// this is the list of all the reasons, for the sales reporting
+    pub const ALL: [RefusalReason; 5] = [
+        RefusalReason::Price,
+        RefusalReason::Timing,
+        RefusalReason::Competitor,
+        RefusalReason::Scope,
+        RefusalReason::Other,
+    ];
+}
+
+/// this is to contain the role of a customer member within its organisation
+#[derive(ScryptoSbor, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CustomerRole {
+    Viewer,
+    Approver,
+}
+
+/// this is to contain data about a customer organisation (a company with several employees holding a customer badge)
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct CustomerOrganisation {
+    pub legal_name_hash: String,
+    pub vat_number_hash: String,
+    pub create_timestamp: Instant,
+    pub members: Vec<NonFungibleLocalId>,
+    pub approvers: Vec<NonFungibleLocalId>,
+}
+
+/// prefix of the offer numbers (e.g. OFF-2026-000123)
+pub const DEFAULT_OFFER_NUMBER_PREFIX: &str = "OFF";
+
+/// seconds after issue when an invoice is due (30 days)
+pub const DEFAULT_PAYMENT_TERMS: i64 = 30 * 24 * 3600;
+
+/// seconds after delivery when escrowed funds are released even if the customer has not confirmed (14 days)
+pub const DEFAULT_ESCROW_TIMEOUT: i64 = 14 * 24 * 3600;
+
+/// this is to contain a priced line of an offer (hardware, licenses, services, ...)
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct OfferLine {
+    pub sku: String,
+    pub description_hash: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub discount: Decimal,
+}
+
+impl OfferLine {
+    /// this is the amount of the line: quantity * unit_price, less the discount (a ratio between 0 and 1),
+    /// rounded to AMOUNT_DECIMAL_PLACES
+    pub fn total(&self) -> Decimal {
+        assert!(self.quantity > Decimal::ZERO, "Quantity must be positive!");
+        assert!(self.unit_price >= Decimal::ZERO, "Unit price cannot be negative!");
+        assert!(self.discount >= Decimal::ZERO && self.discount <= Decimal::ONE, "Discount must be between 0 and 1!");
+
+        self.quantity
+            .checked_mul(self.unit_price)
+            .and_then(|gross| gross.checked_mul(Decimal::ONE - self.discount))
+            .and_then(|net| net.checked_round(AMOUNT_DECIMAL_PLACES, RoundingMode::ToNearestMidpointAwayFromZero))
+            .expect("Offer line amount overflow!")
+    }
+
+    /// this is the amount of an offer made of these lines
+    pub fn total_of(lines: &[OfferLine]) -> Decimal {
+        assert!(!lines.is_empty(), "An offer needs at least one line!");
+        let total = lines
+            .iter()
+            .try_fold(Decimal::ZERO, |total, line| total.checked_add(line.total()))
+            .expect("Offer amount overflow!");
+        assert!(total > Decimal::ZERO, "Amount must be positive!");
+        total
+    }
+}
+
+/// this is to contain the terms of a milestone of a project offer, as given by the manager
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct MilestoneTerms {
+    pub description_hash: String,
+    pub amount: Decimal,
+    pub due_date: Instant,
+}
+
+/// this is to contain the signers required to accept an offer, as given by the manager
+/// (the customer badge the offer is sent to is always the first signer)
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct QuorumTerms {
+    pub co_signers: Vec<NonFungibleLocalId>,
+    pub threshold: u32,
+    pub refusal_by_signer: bool,
+}
+
+/// this is to contain a milestone of a project offer and its progress
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct Milestone {
+    pub description_hash: String,
+    pub amount: Decimal,
+    pub due_date: Instant,
+    pub delivered_timestamp: Option<Instant>,
+    pub paid_timestamp: Option<Instant>,
+}
+
+impl Milestone {
+    /// this is a new milestone, neither delivered nor paid
+    pub fn from_terms(terms: MilestoneTerms) -> Milestone {
+        assert!(terms.amount > Decimal::ZERO, "Milestone amount must be positive!");
+        Milestone {
+            description_hash: terms.description_hash,
+            amount: terms.amount,
+            due_date: terms.due_date,
+            delivered_timestamp: None,
+            paid_timestamp: None,
+        }
+    }
+
+    /// this is the amount of an offer paid with these milestones
+    pub fn total_of(milestones: &[Milestone]) -> Decimal {
+        milestones
+            .iter()
+            .try_fold(Decimal::ZERO, |total, milestone| total.checked_add(milestone.amount))
+            .expect("Milestones amount overflow!")
+    }
+}
+
 /// this is to contain data about an offer
-#[derive(ScryptoSbor, NonFungibleData)]
+#[derive(ScryptoSbor, NonFungibleData, Clone)]
 pub struct OfferData {
+    pub offer_number: String,
     pub hash_pdf: String,
     #[mutable]
-    pub expiry_date: Decimal,
+    pub expiry_date: Instant,
+    #[mutable]
+    pub state: OfferState,    
+    pub create_timestamp: Instant,
+    #[mutable]
+    pub accepted_timestamp: Option<Instant>,
+    #[mutable]
+    pub refused_timestamp: Option<Instant>,
+    #[mutable]
+    pub cancelled_timestamp: Option<Instant>,
+    #[mutable]
+    pub expired_timestamp: Option<Instant>,
+    #[mutable]
+    pub paid_timestamp: Option<Instant>,
+    #[mutable]
+    pub delivered_timestamp: Option<Instant>,
     #[mutable]
-    pub state: String,    
-    pub create_timestamp: Decimal,
+    pub lines: Vec<OfferLine>,
     #[mutable]
-    pub accepted_timestamp: Decimal,
+    pub offer_amount: Decimal,
+    pub currency: ResourceAddress,
+    pub escrow: bool,
+    pub deposit_ratio: Decimal,
     #[mutable]
-    pub refused_timestamp: Decimal,
-    pub offer_amount: Decimal                
+    pub amount_paid: Decimal,
+    #[mutable]
+    pub amount_outstanding: Decimal,
+    #[mutable]
+    pub milestones: Vec<Milestone>,
+    #[mutable]
+    pub version: u32,
+    #[mutable]
+    pub refusal_reason: Option<RefusalReason>,
+    #[mutable]
+    pub refusal_note_hash: Option<String>,
+    #[mutable]
+    pub internal_rejection_hash: Option<String>,
+    pub signers: Vec<NonFungibleLocalId>,
+    pub approval_threshold: u32,
+    pub refusal_by_signer: bool,
+    #[mutable]
+    pub approvals: Vec<NonFungibleLocalId>,
+    #[mutable]
+    pub refusals: Vec<NonFungibleLocalId>,
+    pub customer_badge: NonFungibleLocalId,
+    pub organisation: Option<u64>,
+    pub issued_by: Option<NonFungibleLocalId>,
+    pub parent_offer: Option<NonFungibleLocalId>
+}
+
+impl OfferData {
+    /// this is the amount to be paid on acceptance: offer_amount * deposit_ratio, rounded up to AMOUNT_DECIMAL_PLACES
+    pub fn deposit_amount(&self) -> Decimal {
+        self.offer_amount
+            .checked_mul(self.deposit_ratio)
+            .and_then(|deposit| deposit.checked_round(AMOUNT_DECIMAL_PLACES, RoundingMode::AwayFromZero))
+            .expect("Deposit amount overflow!")
+    }
+}
+
+/// this is to contain the changes requested by a manager when amending an offer
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct OfferAmendment {
+    pub expiry_date: Option<Instant>,
+    pub lines: Option<Vec<OfferLine>>,
+}
+
+/// this is to contain a previous version of an amended offer
+#[derive(ScryptoSbor, Clone)]
+pub struct OfferVersion {
+    pub version: u32,
+    pub expiry_date: Instant,
+    pub lines: Vec<OfferLine>,
+    pub offer_amount: Decimal,
+    pub amended_timestamp: Instant,
+}
+
+/// this is to contain a counter-proposal sent by a customer for an offer
+#[derive(ScryptoSbor, Clone)]
+pub struct CounterProposal {
+    pub offer_amount: Decimal,
+    pub hash_pdf: String,
+    pub note_hash: String,
+    pub customer_badge: NonFungibleLocalId,
+    pub create_timestamp: Instant,
+}
+
+/// this is to contain data about an invoice issued for an accepted offer or a delivered milestone
+#[derive(ScryptoSbor, NonFungibleData, Clone)]
+pub struct InvoiceData {
+    pub invoice_number: u64,
+    pub offer_id: NonFungibleLocalId,
+    pub milestone_index: Option<u32>,
+    pub amount: Decimal,
+    pub tax_amount: Decimal,
+    pub currency: ResourceAddress,
+    pub due_date: Instant,
+    pub create_timestamp: Instant,
+    pub customer_badge: NonFungibleLocalId,
 }
 
 /// this is to contain the username of a Manager Member
 #[derive(NonFungibleData, ScryptoSbor)]
 struct ManagerBadge {
-    username: String
+    username: String,
+    #[mutable]
+    senior: bool,
+    #[mutable]
+    max_offer_amount: Option<Decimal>,
+    #[mutable]
+    max_discount: Option<Decimal>,
+    #[mutable]
+    allowed_currencies: Vec<ResourceAddress>
 }
 
-/// this is to contain the username of a Customer Member
+/// this is to contain the username of a Customer Member, its organisation and its role in there
 #[derive(NonFungibleData, ScryptoSbor)]
 struct CustomerBadge {
-    username: String
+    username: String,
+    organisation: Option<u64>,
+    #[mutable]
+    role: CustomerRole
+}
+
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct OfferStateChangedEvent {
+    offer_id: NonFungibleLocalId,
+    from: OfferState,
+    to: OfferState,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CreatedOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
 }
 
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct SentOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
 
 #[derive(ScryptoSbor, ScryptoEvent)]
 struct AcceptedOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct InternalApprovalEvent {
+    offer_id: NonFungibleLocalId,
+    approved: bool,
+    reason_hash: Option<String>,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct OfferApprovalEvent {
+    offer_id: NonFungibleLocalId,
+    signer_badge: NonFungibleLocalId,
+    approved: bool,
+    reason: Option<RefusalReason>,
+    note_hash: Option<String>,
+    approvals: u32,
+    threshold: u32,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct PaidOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    amount: Decimal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct InvoiceIssuedEvent {
+    offer_id: NonFungibleLocalId,
+    invoice_id: NonFungibleLocalId,
+    invoice: InvoiceData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct MilestoneDeliveredEvent {
+    offer_id: NonFungibleLocalId,
+    index: u32,
+    milestone: Milestone,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct MilestonePaidEvent {
+    offer_id: NonFungibleLocalId,
+    index: u32,
+    milestone: Milestone,
+    amount: Decimal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct DeliveredOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct EscrowReleasedEvent {
+    offer_id: NonFungibleLocalId,
     offer: OfferData,
-    epoch: Decimal,
+    amount: Decimal,
+    by_timeout: bool,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
 }
 
 #[derive(ScryptoSbor, ScryptoEvent)]
 struct RefusedOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    reason: RefusalReason,
+    note_hash: Option<String>,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct BadgeMintedEvent {
+    badge_resource: ResourceAddress,
+    badge_id: NonFungibleLocalId,
+    username: String,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct OrganisationCreatedEvent {
+    organisation_id: u64,
+    organisation: CustomerOrganisation,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CustomerRoleChangedEvent {
+    badge_id: NonFungibleLocalId,
+    from: CustomerRole,
+    to: CustomerRole,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct ManagerUpdatedEvent {
+    badge_id: NonFungibleLocalId,
+    senior: bool,
+    max_offer_amount: Option<Decimal>,
+    max_discount: Option<Decimal>,
+    allowed_currencies: Vec<ResourceAddress>,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct BadgeRevokedEvent {
+    badge_resource: ResourceAddress,
+    badge_id: NonFungibleLocalId,
+    username: String,
+    cancelled_offers: Vec<NonFungibleLocalId>,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CancelledOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    burned: bool,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct ExpiredOfferEvent {
+    offer_id: NonFungibleLocalId,
     offer: OfferData,
-    epoch: Decimal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct OfferAmendedEvent {
+    offer_id: NonFungibleLocalId,
+    previous: OfferVersion,
+    changes: OfferAmendment,
+    version: u32,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CounterOfferEvent {
+    offer_id: NonFungibleLocalId,
+    counter: CounterProposal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CounterAcceptedEvent {
+    offer_id: NonFungibleLocalId,
+    new_offer_id: NonFungibleLocalId,
+    counter: CounterProposal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+#[derive(ScryptoSbor, ScryptoEvent)]
+struct CounterRejectedEvent {
+    offer_id: NonFungibleLocalId,
+    counter: CounterProposal,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
 }
 
 #[blueprint]
-#[events(AcceptedOfferEvent, RefusedOfferEvent)]
+#[events(OfferStateChangedEvent, CreatedOfferEvent, SentOfferEvent, InternalApprovalEvent, AcceptedOfferEvent, OfferApprovalEvent, PaidOfferEvent, InvoiceIssuedEvent, MilestoneDeliveredEvent, MilestonePaidEvent, DeliveredOfferEvent, EscrowReleasedEvent, RefusedOfferEvent, CancelledOfferEvent, BadgeMintedEvent, BadgeRevokedEvent, OrganisationCreatedEvent, CustomerRoleChangedEvent, ManagerUpdatedEvent, ExpiredOfferEvent, OfferAmendedEvent, CounterOfferEvent, CounterAcceptedEvent, CounterRejectedEvent)]
 mod fcgsales {
     enable_method_auth! {
         roles {
@@ -99,25 +647,138 @@ mod fcgsales {
         methods {
             send_offer => restrict_to: [manager, admin, OWNER];
             cancel_offer => restrict_to: [manager, admin, OWNER];
+            amend_offer => restrict_to: [manager, admin, OWNER];
             accept_offer => restrict_to: [customer];
+            accept_and_pay => restrict_to: [customer];
+            pay_balance => restrict_to: [customer];
+            mark_milestone_delivered => restrict_to: [manager, admin, OWNER];
+            pay_milestone => restrict_to: [customer];
+            claim_invoices => restrict_to: [customer];
+            mark_delivered => restrict_to: [manager, admin, OWNER];
+            confirm_delivery => restrict_to: [customer];
+            release_escrow => PUBLIC;
             refuse_offer => restrict_to: [customer];
+            counter_offer => restrict_to: [customer];
+            accept_counter => restrict_to: [manager, admin, OWNER];
+            reject_counter => restrict_to: [manager, admin, OWNER];
+            expire_offers => PUBLIC;
+            get_offer_history => PUBLIC;
+            get_offer_by_number => PUBLIC;
+            offers_by_customer => PUBLIC;
+            offers_by_manager => PUBLIC;
+            offers_by_state => PUBLIC;
+            open_offers_expiring_before => PUBLIC;
+            set_offer_number_prefix => restrict_to: [admin, OWNER];
             mint_manager_badge => restrict_to: [admin, OWNER];
             mint_customer_badge => restrict_to: [manager, admin, OWNER];
+            list_managers => PUBLIC;
+            list_customers => PUBLIC;
+            find_manager => PUBLIC;
+            find_customer => PUBLIC;
+            create_organisation => restrict_to: [manager, admin, OWNER];
+            get_organisation => PUBLIC;
+            set_customer_role => restrict_to: [manager, admin, OWNER];
+            revoke_manager => restrict_to: [admin, OWNER];
+            revoke_customer => restrict_to: [manager, admin, OWNER];
+            cancel_customer_offers => restrict_to: [manager, admin, OWNER];
+            set_burn_on_cancel => restrict_to: [admin, OWNER];
+            add_currency => restrict_to: [admin, OWNER];
+            remove_currency => restrict_to: [admin, OWNER];
+            get_currencies => PUBLIC;
+            get_refusal_counts => PUBLIC;
+            approve_offer => restrict_to: [manager, admin, OWNER];
+            reject_internal => restrict_to: [manager, admin, OWNER];
+            set_approval_limit => restrict_to: [admin, OWNER];
+            set_senior_manager => restrict_to: [admin, OWNER];
+            set_manager_limits => restrict_to: [admin, OWNER];
+            withdraw_treasury => restrict_to: [admin, OWNER];
+            set_escrow_timeout => restrict_to: [admin, OWNER];
+            set_tax_rate => restrict_to: [admin, OWNER];
+            set_payment_terms => restrict_to: [admin, OWNER];
+            get_invoices => PUBLIC;
         }
     }
 
     /// Data managed by the blueprint
     // nft_manager: ResourceManager,                            -> Resource Manager for minting/updating OfferData NFT
-    // manager: AvlTree<u16, NonFungibleLocalId>,               -> List of manager members NonFungibleLocalId
-    // customer: AvlTree<u16, NonFungibleLocalId>,              -> List of customer members NonFungibleLocalId
+    // manager: AvlTree<u64, String>,                           -> List of manager members username, by badge id
+    // customer: AvlTree<u64, String>,                          -> List of customer members username, by badge id
+    // manager_usernames: KeyValueStore<String, NonFungibleLocalId>  -> Manager badges, by username (a username is unique)
+    // customer_usernames: KeyValueStore<String, NonFungibleLocalId> -> Customer badges, by username (a username is unique)
+    // organisations: KeyValueStore<u64, CustomerOrganisation>   -> Customer organisations, by organisation id
+    // organisation_counter: u64,                                -> Id of the last customer organisation created
+    // manager_counter: u64,                                     -> Id of the last manager badge minted, ids are never reused
+    // customer_counter: u64,                                    -> Id of the last customer badge minted, ids are never reused
     // manager_badge_resource_manager: ResourceManager,         -> Resource manager for minting/burning/recalling manager badges
     // customer_badge_resource_manager: ResourceManager,        -> Resource manager for minting/burning/recalling a customer badges
+    // cancelled_offers: NonFungibleVault,                       -> Vault keeping the OfferData NFT recalled by a cancellation
+    // burn_on_cancel: bool,                                     -> If true a cancelled OfferData NFT is burned instead of being kept
+    // open_offers: AvlTree<(i64, NonFungibleLocalId), NonFungibleLocalId> -> Offers still in the NEW state, sorted by expiry date
+    // counter_offers: KeyValueStore<NonFungibleLocalId, CounterProposal>   -> Pending counter-proposals, by id of the countered offer
+    // offer_history: KeyValueStore<NonFungibleLocalId, Vec<OfferVersion>>  -> Previous versions of the amended offers
+    // currencies: Vec<ResourceAddress>,                         -> Currencies an offer can be denominated in
+    // treasury: KeyValueStore<ResourceAddress, Vault>,          -> Payments received for the offers, one vault per currency
+    // escrow_vaults: KeyValueStore<NonFungibleLocalId, Vault>,  -> Payments held in escrow until delivery, one vault per offer
+    // escrow_timeout: i64,                                      -> Seconds after delivery when escrowed funds can be released without the customer
+    // invoice_manager: ResourceManager,                         -> Resource Manager for minting Invoice NFT
+    // invoice_counter: u64,                                     -> Number of the last invoice issued
+    // invoices: KeyValueStore<NonFungibleLocalId, Vec<NonFungibleLocalId>> -> Invoices issued, by id of the offer (the seller copy)
+    // unclaimed_invoices: KeyValueStore<NonFungibleLocalId, NonFungibleVault> -> Invoices of the delivered milestones, by id of the offer, until the customer claims them
+    // tax_rate: Decimal,                                        -> Tax applied to the amount of an invoice
+    // payment_terms: i64,                                       -> Seconds after issue when an invoice is due
+    // offer_number_prefix: String,                              -> Prefix of the offer numbers
+    // offer_number_year: i64,                                   -> Year of the last offer number issued, the sequence restarts every year
+    // offer_number_counter: u64,                                -> Sequence of the last offer number issued in the year
+    // offers_by_number: KeyValueStore<String, NonFungibleLocalId> -> Offers, by offer number
+    // refusal_counts: KeyValueStore<RefusalReason, u64>,        -> Number of offers refused, by reason
+    // approval_limits: KeyValueStore<ResourceAddress, Decimal>, -> Amount above which an offer needs an internal approval, by currency
+    // pending_offers: NonFungibleVault,                         -> Vault keeping the OfferData NFT waiting for an internal approval
+    // admin_rule: AccessRule,                                   -> Rule satisfied by the Owner or the Admin badge
+    // offer_counter: u64,                                       -> Sequence of the last offer minted, orders the indexes of the offers
+    // offer_sequence: KeyValueStore<NonFungibleLocalId, u64>,   -> Sequence of the offers, by id of the offer
+    // offers_by_customer: AvlTree<(u64, u64), NonFungibleLocalId> -> Offers, by customer badge id and sequence
+    // offers_by_manager: AvlTree<(u64, u64), NonFungibleLocalId>  -> Offers sent by a manager, by manager badge id and sequence
+    // offers_by_state: AvlTree<(OfferState, u64), NonFungibleLocalId> -> Offers, by state and sequence (updated on every transition)
     struct Fcgsales<> {
         nft_manager: ResourceManager,
-        manager: AvlTree<u16, NonFungibleLocalId>,
-        customer: AvlTree<u16, NonFungibleLocalId>,
+        manager: AvlTree<u64, String>,
+        customer: AvlTree<u64, String>,
+        manager_usernames: KeyValueStore<String, NonFungibleLocalId>,
+        customer_usernames: KeyValueStore<String, NonFungibleLocalId>,
+        organisations: KeyValueStore<u64, CustomerOrganisation>,
+        organisation_counter: u64,
+        manager_counter: u64,
+        customer_counter: u64,
         manager_badge_resource_manager: ResourceManager,
         customer_badge_resource_manager: ResourceManager,
+        cancelled_offers: NonFungibleVault,
+        burn_on_cancel: bool,
+        open_offers: AvlTree<(i64, NonFungibleLocalId), NonFungibleLocalId>,
+        counter_offers: KeyValueStore<NonFungibleLocalId, CounterProposal>,
+        offer_history: KeyValueStore<NonFungibleLocalId, Vec<OfferVersion>>,
+        currencies: Vec<ResourceAddress>,
+        treasury: KeyValueStore<ResourceAddress, Vault>,
+        escrow_vaults: KeyValueStore<NonFungibleLocalId, Vault>,
+        escrow_timeout: i64,
+        invoice_manager: ResourceManager,
+        invoice_counter: u64,
+        invoices: KeyValueStore<NonFungibleLocalId, Vec<NonFungibleLocalId>>,
+        unclaimed_invoices: KeyValueStore<NonFungibleLocalId, NonFungibleVault>,
+        tax_rate: Decimal,
+        payment_terms: i64,
+        offer_number_prefix: String,
+        offer_number_year: i64,
+        offer_number_counter: u64,
+        offers_by_number: KeyValueStore<String, NonFungibleLocalId>,
+        refusal_counts: KeyValueStore<RefusalReason, u64>,
+        approval_limits: KeyValueStore<ResourceAddress, Decimal>,
+        pending_offers: NonFungibleVault,
+        admin_rule: AccessRule,
+        offer_counter: u64,
+        offer_sequence: KeyValueStore<NonFungibleLocalId, u64>,
+        offers_by_customer: AvlTree<(u64, u64), NonFungibleLocalId>,
+        offers_by_manager: AvlTree<(u64, u64), NonFungibleLocalId>,
+        offers_by_state: AvlTree<(OfferState, u64), NonFungibleLocalId>,
     }
 
     impl Fcgsales {
@@ -127,7 +788,7 @@ mod fcgsales {
         /// This create also:
         ///   - resource managers to manage customer and manager badges
         ///   - a NFT manager to mint/recall OfferData 
-        ///   - two separate containers to store customer and manager list of NonFungibleLocalId (not needed)
+        ///   - two separate registries of the customer and manager members, with an index by username
         /// 
         /// Returns a tuple containing:
         /// - The component address of the instantiated and globalized Fcgsales
@@ -137,8 +798,8 @@ mod fcgsales {
         pub fn instantiate() -> (Global<Fcgsales>, FungibleBucket, FungibleBucket) {
 
             //container
-            let manager: AvlTree<u16, NonFungibleLocalId> = AvlTree::new();
-            let customer: AvlTree<u16, NonFungibleLocalId> = AvlTree::new();
+            let manager: AvlTree<u64, String> = AvlTree::new();
+            let customer: AvlTree<u64, String> = AvlTree::new();
 
             let (address_reservation, component_address) =
                 Runtime::allocate_component_address(Fcgsales::blueprint_id());
@@ -204,6 +865,10 @@ mod fcgsales {
                     );
                     recaller_updater => OWNER;
                 })
+                .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                    non_fungible_data_updater => rule!(require(global_caller(component_address)));
+                    non_fungible_data_updater_updater => OWNER;
+                ))
             .create_with_no_initial_supply();           
 
 
@@ -241,6 +906,10 @@ mod fcgsales {
                         || require(manager_resource_manager.address()));
                     recaller_updater => OWNER;
                 })
+                .non_fungible_data_update_roles(non_fungible_data_update_roles!(
+                    non_fungible_data_updater => rule!(require(global_caller(component_address)));
+                    non_fungible_data_updater_updater => OWNER;
+                ))
             .create_with_no_initial_supply();             
                 
 
@@ -279,6 +948,27 @@ mod fcgsales {
                     non_fungible_data_updater_updater => OWNER;
                 ))           
                 .create_with_no_initial_supply();
+
+            // Create a resourceManager to manage Invoice NFT
+            // The local id of an Invoice NFT is its sequential invoice number
+            // Mint is available only from the component
+            let invoice_manager =
+                ResourceBuilder::new_integer_non_fungible::<InvoiceData>(OwnerRole::Updatable(rule!(
+                    require(owner_badge.resource_address())
+                        || require(admin_badge.resource_address())
+                )))
+                .metadata(metadata!(
+                    init {
+                        "name" => "FCG Sales Invoice NFT", locked;
+                        "symbol" => "FCG Sales Invoice", locked;
+                        "description" => "An NFT containing an Invoice for an accepted Offer", locked;
+                    }
+                ))
+                .mint_roles(mint_roles!(
+                    minter => rule!(require(global_caller(component_address)));
+                    minter_updater => rule!(require(global_caller(component_address)));
+                ))
+                .create_with_no_initial_supply();
       
 
             // Populate a Fcgsales struct and instantiate a new component
@@ -288,8 +978,42 @@ mod fcgsales {
                     nft_manager: nft_manager,
                     manager: manager,
                     customer: customer,
+                    manager_usernames: KeyValueStore::new(),
+                    customer_usernames: KeyValueStore::new(),
+                    organisations: KeyValueStore::new(),
+                    organisation_counter: 0,
+                    manager_counter: 0,
+                    customer_counter: 0,
                     manager_badge_resource_manager: manager_resource_manager,
                     customer_badge_resource_manager: customer_resource_manager,
+                    cancelled_offers: NonFungibleVault::new(nft_manager.address()),
+                    burn_on_cancel: false,
+                    open_offers: AvlTree::new(),
+                    counter_offers: KeyValueStore::new(),
+                    offer_history: KeyValueStore::new(),
+                    currencies: Vec::new(),
+                    treasury: KeyValueStore::new(),
+                    escrow_vaults: KeyValueStore::new(),
+                    escrow_timeout: DEFAULT_ESCROW_TIMEOUT,
+                    invoice_manager: invoice_manager,
+                    invoice_counter: 0,
+                    invoices: KeyValueStore::new(),
+                    unclaimed_invoices: KeyValueStore::new(),
+                    tax_rate: Decimal::ZERO,
+                    payment_terms: DEFAULT_PAYMENT_TERMS,
+                    offer_number_prefix: DEFAULT_OFFER_NUMBER_PREFIX.to_string(),
+                    offer_number_year: 0,
+                    offer_number_counter: 0,
+                    offers_by_number: KeyValueStore::new(),
+                    refusal_counts: KeyValueStore::new(),
+                    approval_limits: KeyValueStore::new(),
+                    pending_offers: NonFungibleVault::new(nft_manager.address()),
+                    admin_rule: rule!(require(owner_badge.resource_address()) || require(admin_badge.resource_address())),
+                    offer_counter: 0,
+                    offer_sequence: KeyValueStore::new(),
+                    offers_by_customer: AvlTree::new(),
+                    offers_by_manager: AvlTree::new(),
+                    offers_by_state: AvlTree::new(),
                 }
                 .instantiate()
                 .prepare_to_globalize(OwnerRole::Updatable(rule!(require(
@@ -309,12 +1033,57 @@ mod fcgsales {
                     init {
                         send_offer => Free, locked;
                         cancel_offer => Free, locked;
+                        amend_offer => Free, locked;
 
                         accept_offer => Free, locked;
+                        accept_and_pay => Free, locked;
+                        pay_balance => Free, locked;
+                        mark_milestone_delivered => Free, locked;
+                        pay_milestone => Free, locked;
+                        claim_invoices => Free, locked;
+                        mark_delivered => Free, locked;
+                        confirm_delivery => Free, locked;
+                        release_escrow => Free, locked;
                         refuse_offer => Free, locked;
+                        expire_offers => Free, locked;
+                        get_offer_history => Free, locked;
+                        get_offer_by_number => Free, locked;
+                        offers_by_customer => Free, locked;
+                        offers_by_manager => Free, locked;
+                        offers_by_state => Free, locked;
+                        open_offers_expiring_before => Free, locked;
+                        set_offer_number_prefix => Free, locked;
+                        counter_offer => Free, locked;
+                        accept_counter => Free, locked;
+                        reject_counter => Free, locked;
 
                         mint_manager_badge => Free, locked;
                         mint_customer_badge => Free, locked;
+                        list_managers => Free, locked;
+                        list_customers => Free, locked;
+                        find_manager => Free, locked;
+                        find_customer => Free, locked;
+                        create_organisation => Free, locked;
+                        get_organisation => Free, locked;
+                        set_customer_role => Free, locked;
+                        revoke_manager => Free, locked;
+                        revoke_customer => Free, locked;
+                        cancel_customer_offers => Free, locked;
+                        set_burn_on_cancel => Free, locked;
+                        add_currency => Free, locked;
+                        remove_currency => Free, locked;
+                        get_currencies => Free, locked;
+                        withdraw_treasury => Free, locked;
+                        set_escrow_timeout => Free, locked;
+                        set_tax_rate => Free, locked;
+                        set_payment_terms => Free, locked;
+                        get_invoices => Free, locked;
+                        get_refusal_counts => Free, locked;
+                        approve_offer => Free, locked;
+                        reject_internal => Free, locked;
+                        set_approval_limit => Free, locked;
+                        set_senior_manager => Free, locked;
+                        set_manager_limits => Free, locked;
                     }
                 })                
                 .metadata(metadata!(
@@ -343,11 +1112,18 @@ mod fcgsales {
         /// 
         /// Arguments:
         /// - `hash_pdf`: This is the hash of the PDF document that contains the commercial offer 
-        /// - `expiry_date`: Expiry date of the offer
-        /// - `customer_account`: Account where this NFT will be sent (not needed)
+        /// - `expiry_date`: Expiry date of the offer (UTC seconds), the same date printed on the PDF document
+        /// - `lines`: Priced lines of the offer, the amount of the offer is their total
+        /// - `currency`: Resource the amount of the offer is denominated in, it has to be an accepted currency
+        /// - `escrow`: if true the payment is held by the component until the customer confirms the delivery
+        /// - `deposit_ratio`: Fraction (between 0 and 1) of the amount to be paid on acceptance, an escrow offer has to be paid in full (1)
+        /// - `milestones`: Payment schedule of a project offer, the amounts have to add up to the amount of the offer (empty if not needed)
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge that is allowed to accept/refuse this offer
+        /// - `quorum`: Other Customer Badges that have to sign the acceptance and how many approvals are needed (none if only the customer badge signs)
+        /// - `manager_proof`: Proof of the Manager Badge, the offer has to be within the limits of the manager (none if sent by an Admin)
         ///
         /// Returns 'Bucket':
-        /// - the OfferData NFT
+        /// - the OfferData NFT (empty if the offer is above the approval limit, it is kept by the component until approve_offer)
         ///
         /// ---
         ///
@@ -358,36 +1134,23 @@ mod fcgsales {
         /// ```text
         #[doc = include_str!("../fcgsales/send_offer_as_manager.rtm")]
         /// ```        
-        pub fn send_offer(&mut self, hash_pdf: String, expiry_date: Decimal, offer_amount: Decimal, _customer_account: Global<Account>) -> Bucket {
+        pub fn send_offer(&mut self, hash_pdf: String, expiry_date: Instant, lines: Vec<OfferLine>, currency: ResourceAddress, escrow: bool, deposit_ratio: Decimal, milestones: Vec<MilestoneTerms>, customer_badge: NonFungibleLocalId, quorum: Option<QuorumTerms>, manager_proof: Option<NonFungibleProof>) -> Bucket {
+            let issued_by = self.check_manager_limits(manager_proof, &lines, currency);
             info!("Ready for minting an offer ");
-            //mint an NFT
-            let epoch = Decimal::from(Runtime::current_epoch().number());
-            let offer = OfferData {
-                hash_pdf: hash_pdf.clone(),
-                expiry_date: expiry_date,
-                state: "NEW".to_string(),    
-                create_timestamp: epoch,
-                accepted_timestamp: dec!(0),
-                refused_timestamp: dec!(0),
-                offer_amount: offer_amount   
-            };
-
-            info!("Minting an offer ");
-            
-            let nft = self
-            .nft_manager
-                .mint_ruid_non_fungible(offer);
-
-            info!("Sending an offer for this pdf {:?} with this expiry date {:?} to this account {:?}  ",hash_pdf, expiry_date, _customer_account);
-
+            let (_, nft) = self.mint_offer(hash_pdf, expiry_date, lines, currency, escrow, deposit_ratio, milestones, customer_badge, quorum, issued_by, None);
             nft
         }
 
-        /// This removes the offer NFT from the customer account
+        /// This removes the offer NFT from the customer account and cancels it
         /// 
+        /// The OfferData NFT is recalled from the vault that holds it (or taken from the component if the offer
+        /// is still waiting for an internal approval), its state is set to CANCELLED 
+        /// and then it is kept by the component, or burned if `burn_on_cancel` has been set by an admin
         /// 
         /// Arguments:
         /// - `offer_id`: NonFungibleLocalId of the NFT to be recalled
+        /// - `offer_vault`: Address of the vault (usually in the customer account) holding the NFT (none for an offer pending approval)
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
         ///
         /// Returns 'None':
         ///
@@ -396,135 +1159,2195 @@ mod fcgsales {
         /// **Access control:** Can be called by an Admin or by a Manager.
         ///
         /// **Transaction manifest:**
-        /// `fcgsales/recall_offer.rtm`
+        /// `fcgsales/cancel_offer.rtm`
         /// ```text
-        #[doc = include_str!("../fcgsales/recall_offer.rtm")]
+        #[doc = include_str!("../fcgsales/cancel_offer.rtm")]
         /// ```    
-        pub fn cancel_offer(&mut self, _offer_id: NonFungibleLocalId)  {
+        pub fn cancel_offer(&mut self, offer_id: NonFungibleLocalId, offer_vault: Option<InternalAddress>, manager_proof: Option<NonFungibleProof>)  {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+            let state = self.nft_manager.get_non_fungible_data::<OfferData>(&offer_id).state;
+
+            // Update the state of the Offer
+            let nfdata = self.transition(&offer_id, OfferState::Cancelled, actor.clone());
+            // a pending counter-proposal is dropped together with the offer
+            self.counter_offers.remove(&offer_id);
+
+            info!("Cancelling an offer for this pdf {:?} with this expiry date {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, now);
+
+            // the OfferData NFT of an offer waiting for an internal approval is still held by the component,
+            // otherwise it is recalled from the vault holding it
+            let recalled: NonFungibleBucket = if state == OfferState::PendingApproval {
+                self.pending_offers.take_non_fungible(&offer_id)
+            } else {
+                let offer_vault = offer_vault.expect("The vault holding the offer is needed to cancel it!");
+                Self::recall_non_fungible(offer_vault, self.nft_manager.address(), &offer_id)
+            };
+
+            if self.burn_on_cancel {
+                recalled.burn();
+            } else {
+                self.cancelled_offers.put(recalled);
+            }
 
-            todo!();
+            //emit the event
+            Runtime::emit_event(CancelledOfferEvent { offer_id: offer_id, offer: nfdata, burned: self.burn_on_cancel, actor: actor, timestamp: now});
         }
 
-        /// This is for accepting an offer
+        /// This is for amending an offer before the customer decides on it
+        /// 
+        /// The expiry date and/or the lines of the offer are updated, the version of the offer is increased 
+        /// and the previous version is kept in the history of the offer.
+        /// The amount of the offer cannot be raised above the approval limit of its currency
         /// 
         /// Arguments:
-        /// - `offer_data_proof`: the OfferData NFT Proof 
+        /// - `offer_id`: NonFungibleLocalId of the offer to be amended
+        /// - `changes`: the new expiry date and/or the new lines of the offer
+        /// - `manager_proof`: Proof of the Manager Badge, the amended offer has to be within the limits of the manager (none if amended by an Admin)
         ///
         /// Returns 'None':
         ///
         /// ---
         ///
-        /// **Access control:** Can be called by a Customer Only.
+        /// **Access control:** Can be called by an Admin or by a Manager.
         ///
         /// **Transaction manifest:**
-        /// `fcgsales/accept_offer.rtm`
+        /// `fcgsales/amend_offer.rtm`
         /// ```text
-        #[doc = include_str!("../fcgsales/accept_offer.rtm")]
-        /// ```        
-        pub fn accept_offer(&mut self, offer_data_proof: NonFungibleProof)  {
-            let current_epoch = Decimal::from(Runtime::current_epoch().number());
-            
-            // Update the state of the Offer
-            let offer_data_proof = offer_data_proof.skip_checking();
-            let nft_local_id: NonFungibleLocalId = offer_data_proof.as_non_fungible().non_fungible_local_id();
-            let mut nfdata: OfferData = ResourceManager::from(offer_data_proof.resource_address()).get_non_fungible_data(&nft_local_id);
+        #[doc = include_str!("../fcgsales/amend_offer.rtm")]
+        /// ```    
+        pub fn amend_offer(&mut self, offer_id: NonFungibleLocalId, changes: OfferAmendment, manager_proof: Option<NonFungibleProof>) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(&offer_id);
+            let amended_by = self.check_manager_limits(manager_proof, changes.lines.as_ref().unwrap_or(&nfdata.lines), nfdata.currency);
+
+            info!("Amending an offer for this pdf {:?} with these changes {:?} at time  {:?} ",nfdata.hash_pdf, changes, now);
+
+            assert!(nfdata.state == OfferState::New, "Only a NEW offer can be amended!");
+            assert!(changes.expiry_date.is_some() || changes.lines.is_some(), "No changes requested!");
+
+            let previous = OfferVersion {
+                version: nfdata.version,
+                expiry_date: nfdata.expiry_date,
+                lines: nfdata.lines.clone(),
+                offer_amount: nfdata.offer_amount,
+                amended_timestamp: now,
+            };
 
-            info!("Accepting an offer for this pdf {:?} with this expiry date {:?} at epoch  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, current_epoch);
+            if let Some(expiry_date) = changes.expiry_date {
+                assert!(now.compare(expiry_date, TimeComparisonOperator::Lt), "Expiry date is already in the past!");
+                self.nft_manager.update_non_fungible_data(&offer_id, "expiry_date", expiry_date);
+                // the open offers are sorted by expiry date
+                self.open_offers.remove(&(nfdata.expiry_date.seconds_since_unix_epoch, offer_id.clone()));
+                self.open_offers.insert((expiry_date.seconds_since_unix_epoch, offer_id.clone()), offer_id.clone());
+            }
+            if let Some(lines) = changes.lines.clone() {
+                let offer_amount = OfferLine::total_of(&lines);
+                assert!(nfdata.milestones.is_empty() || Milestone::total_of(&nfdata.milestones) == offer_amount, "Milestones have to add up to the amount of the offer!");
+                // an amendment cannot raise an offer above the approval limit of its currency, it would skip the internal approval
+                if let Some(limit) = self.approval_limits.get(&nfdata.currency) {
+                    assert!(offer_amount <= *limit || offer_amount <= nfdata.offer_amount, "Amended amount is above the approval limit, send a new offer!");
+                }
+                self.nft_manager.update_non_fungible_data(&offer_id, "lines", lines);
+                self.nft_manager.update_non_fungible_data(&offer_id, "offer_amount", offer_amount);
+                self.nft_manager.update_non_fungible_data(&offer_id, "amount_outstanding", offer_amount);
+            }
+            let version = nfdata.version + 1;
+            self.nft_manager.update_non_fungible_data(&offer_id, "version", version);
+            // the signers have to decide again on the amended offer
+            self.nft_manager.update_non_fungible_data(&offer_id, "approvals", Vec::<NonFungibleLocalId>::new());
+            self.nft_manager.update_non_fungible_data(&offer_id, "refusals", Vec::<NonFungibleLocalId>::new());
 
-            assert!(nfdata.state == "NEW", "Offer is not acceptable anymore!");
-            assert!(nfdata.expiry_date >= current_epoch, "Offer is expired!");
-            self.nft_manager.update_non_fungible_data(&nft_local_id, "state", "ACCEPTED");   
-            self.nft_manager.update_non_fungible_data(&nft_local_id, "accepted_timestamp", current_epoch);   
+            let mut history = self.offer_history.get(&offer_id).map(|history| history.to_vec()).unwrap_or_default();
+            history.push(previous.clone());
+            self.offer_history.insert(offer_id.clone(), history);
 
             //emit the event
-            nfdata.state = "ACCEPTED".to_string();
-            Runtime::emit_event(AcceptedOfferEvent { offer: nfdata, epoch: current_epoch});
+            Runtime::emit_event(OfferAmendedEvent { offer_id: offer_id, previous: previous, changes: changes, version: version, actor: self.manager_actor(amended_by), timestamp: now});
         }
 
-        /// This is for refusing an offer
+        /// This is for approving internally an offer above the approval limit of its currency
+        /// 
+        /// The offer is moved from PENDING_APPROVAL to NEW and its OfferData NFT is given back to be sent to the customer
         /// 
         /// Arguments:
-        /// - `offer_data_proof`: the OfferData NFT Proof 
+        /// - `offer_id`: NonFungibleLocalId of the offer to be approved
+        /// - `manager_proof`: Proof of the Manager Badge of a senior manager, other than the one who sent the offer (none if approved by an Admin)
         ///
-        /// Returns 'None':
+        /// Returns 'Bucket':
+        /// - the OfferData NFT, to be deposited to the customer
         ///
         /// ---
         ///
-        /// **Access control:** Can be called by a Customer Only.
+        /// **Access control:** Can be called by an Admin or by a senior Manager who has not sent the offer.
         ///
         /// **Transaction manifest:**
-        /// `fcgsales/refuse_offer.rtm`
+        /// `fcgsales/approve_offer.rtm`
         /// ```text
-        #[doc = include_str!("../fcgsales/refuse_offer.rtm")]
-        /// ```      
-        pub fn refuse_offer(&mut self, offer_data_proof: NonFungibleProof) {
-            let current_epoch = Decimal::from(Runtime::current_epoch().number());
+        #[doc = include_str!("../fcgsales/approve_offer.rtm")]
+        /// ```    
+        pub fn approve_offer(&mut self, offer_id: NonFungibleLocalId, manager_proof: Option<NonFungibleProof>) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+            let approver = self.check_approver(manager_proof);
 
-            // Update the state of the Offer
-            let offer_data_proof = offer_data_proof.skip_checking();
-            let nft_local_id: NonFungibleLocalId = offer_data_proof.as_non_fungible().non_fungible_local_id();
-            let mut nfdata: OfferData = ResourceManager::from(offer_data_proof.resource_address()).get_non_fungible_data(&nft_local_id);
-            
-            info!("Refuse an offer for this pdf {:?} with this expiry date {:?} at epoch  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, current_epoch);
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(&offer_id);
+            assert!(now.compare(nfdata.expiry_date, TimeComparisonOperator::Lt), "Offer is expired!");
+            // the four-eyes principle: a manager cannot approve an offer it has sent
+            assert!(approver.is_none() || nfdata.issued_by != approver, "A manager cannot approve its own offer!");
+            let actor = self.manager_actor(approver);
 
-            assert!(nfdata.state == "NEW", "Offer is not refusable anymore!");
-            assert!(nfdata.expiry_date >= current_epoch, "Offer is expired!");
-            self.nft_manager.update_non_fungible_data(&nft_local_id, "state", "REFUSED");   
-            self.nft_manager.update_non_fungible_data(&nft_local_id, "refused_timestamp", current_epoch);   
+            info!("Approving an offer for this pdf {:?} with this amount {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.offer_amount, now);
 
-            //emit the event
-            nfdata.state = "REFUSED".to_string();
-            Runtime::emit_event(RefusedOfferEvent { offer: nfdata, epoch: current_epoch});
+            let nfdata = self.transition(&offer_id, OfferState::New, actor.clone());
+
+            //emit the events, the approved offer is sent to the customer only now
+            Runtime::emit_event(InternalApprovalEvent { offer_id: offer_id.clone(), approved: true, reason_hash: None, actor: actor.clone(), timestamp: now});
+            Runtime::emit_event(SentOfferEvent { offer_id: offer_id.clone(), offer: nfdata, actor: actor, timestamp: now});
+
+            self.pending_offers.take_non_fungible(&offer_id).into()
         }
 
-        /// Utility function: Mint a manager badge
+        /// This is for rejecting internally an offer above the approval limit of its currency
+        /// 
+        /// The offer is moved from PENDING_APPROVAL to DRAFT with the reason of the rejection, 
+        /// and its OfferData NFT is given back to be returned to the manager (a DRAFT offer can only be cancelled)
         /// 
         /// Arguments:
-        /// - `username`: Username that will be registered in the NFT
+        /// - `offer_id`: NonFungibleLocalId of the offer to be rejected
+        /// - `reason_hash`: Hash of a note explaining the rejection
+        /// - `manager_proof`: Proof of the Manager Badge of a senior manager (none if rejected by an Admin)
+        ///
+        /// Returns 'Bucket':
+        /// - the OfferData NFT, to be deposited to the manager
+        ///
         /// ---
         ///
-        /// **Access control:** Can be called by the Owner or the Admin only.
-        ///                    
-        pub fn mint_manager_badge(&mut self, username: String) -> Bucket {
-            let manager_badge_bucket: Bucket = self
-                .manager_badge_resource_manager
-                .mint_non_fungible(
-                    &NonFungibleLocalId::integer((self.manager.get_length()+1).try_into().unwrap()),
-                    ManagerBadge {
-                        username: username.clone(),
-                    });
+        /// **Access control:** Can be called by an Admin or by a senior Manager.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/reject_internal.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/reject_internal.rtm")]
+        /// ```    
+        pub fn reject_internal(&mut self, offer_id: NonFungibleLocalId, reason_hash: String, manager_proof: Option<NonFungibleProof>) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_approver(manager_proof));
 
-            let id = manager_badge_bucket.as_non_fungible().non_fungible_local_id();
-            let key = self.manager.get_length().to_u16().unwrap()+1; 
-            info!("Saving staff badge with key : {:?} and id {:?} for the username: {:?}  ",key, id, username);
-            self.manager.insert(key, id);
+            self.nft_manager.update_non_fungible_data(&offer_id, "internal_rejection_hash", Some(reason_hash.clone()));
+            let nfdata = self.transition(&offer_id, OfferState::Draft, actor.clone());
 
-            manager_badge_bucket
+            info!("Rejecting an offer for this pdf {:?} with this amount {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.offer_amount, now);
+
+            //emit the event
+            Runtime::emit_event(InternalApprovalEvent { offer_id: offer_id.clone(), approved: false, reason_hash: Some(reason_hash), actor: actor, timestamp: now});
+
+            self.pending_offers.take_non_fungible(&offer_id).into()
+        }
+
+        /// This is for accepting an offer
+        /// 
+        /// Every call records the approval of a signer, the offer is ACCEPTED only when the approval threshold is reached
+        /// (the last signer of an escrow offer or of an offer with a deposit has to use accept_and_pay)
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of the signer
+        ///
+        /// Returns 'Option<Bucket>':
+        /// - the Invoice NFT of the offer (none until the offer is accepted, or for a milestone offer, invoiced by milestone)
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/accept_offer.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/accept_offer.rtm")]
+        /// ```        
+        pub fn accept_offer(&mut self, offer_id: NonFungibleLocalId, signer_badge: NonFungibleLocalId) -> Option<Bucket> {
+            let (nfdata, invoice) = self.accept(&offer_id, signer_badge);
+            if nfdata.state == OfferState::Accepted {
+                assert!(!nfdata.escrow, "An escrow offer has to be paid on acceptance, use accept_and_pay!");
+                assert!(nfdata.deposit_amount() == Decimal::ZERO, "A deposit has to be paid on acceptance, use accept_and_pay!");
+            }
+            invoice
+        }
+
+        /// This is for accepting an offer and paying it in the same transaction
+        /// 
+        /// The payment has to be in the currency of the offer and at least the deposit of the offer,
+        /// up to the amount of the offer is kept in the treasury of the component (or in the escrow vault
+        /// of the offer for an escrow offer) and the rest is given back.
+        /// The offer is FULLY_PAID if the whole amount has been paid, PARTIALLY_PAID otherwise
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of the signer, its approval has to reach the approval threshold
+        /// - `payment`: Bucket with the payment of the offer
+        ///
+        /// Returns '(Bucket, Option<Bucket>)':
+        /// - the change of the payment
+        /// - the Invoice NFT of the offer
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/accept_and_pay.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/accept_and_pay.rtm")]
+        /// ```        
+        pub fn accept_and_pay(&mut self, offer_id: NonFungibleLocalId, signer_badge: NonFungibleLocalId, mut payment: Bucket) -> (Bucket, Option<Bucket>) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let actor = self.customer_actor(&signer_badge);
+            let (nfdata, invoice) = self.accept(&offer_id, signer_badge);
+            assert!(nfdata.state == OfferState::Accepted, "Approval threshold not reached yet, approve with accept_offer!");
+
+            assert!(nfdata.milestones.is_empty(), "A milestone offer is paid by milestone, use pay_milestone!");
+            assert!(payment.resource_address() == nfdata.currency, "Payment is not in the currency of the offer!");
+            assert!(payment.amount() >= nfdata.deposit_amount(), "Payment is lower than the deposit of the offer!");
+            let amount = if payment.amount() < nfdata.amount_outstanding { payment.amount() } else { nfdata.amount_outstanding };
+            assert!(amount > Decimal::ZERO, "Payment is empty!");
+
+            info!("Paying an offer for this pdf {:?} with this amount {:?} at time  {:?} ",nfdata.hash_pdf, amount, now);
+
+            let offer_payment = payment.take(amount);
+            let amount_outstanding = self.record_payment(&offer_id, &nfdata, amount);
+            let nfdata = if nfdata.escrow {
+                self.escrow_vaults.insert(offer_id.clone(), Vault::with_bucket(offer_payment));
+                self.transition(&offer_id, OfferState::Escrowed, actor.clone())
+            } else {
+                self.deposit_to_treasury(offer_payment);
+                if amount_outstanding == Decimal::ZERO {
+                    self.transition(&offer_id, OfferState::FullyPaid, actor.clone())
+                } else {
+                    self.transition(&offer_id, OfferState::PartiallyPaid, actor.clone())
+                }
+            };
+
+            //emit the event
+            Runtime::emit_event(PaidOfferEvent { offer_id: offer_id, offer: nfdata, amount: amount, actor: actor, timestamp: now});
+
+            (payment, invoice)
+        }
+
+        /// This is for paying the balance of an accepted offer (not an escrow one)
+        /// 
+        /// The payment has to be in the currency of the offer and at least the outstanding amount of the offer,
+        /// the outstanding amount is kept in the treasury of the component, the rest is given back and the offer is FULLY_PAID
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge of a signer of the offer (of a member of the organisation, for an organisation offer)
+        /// - `payment`: Bucket with the payment of the balance
+        ///
+        /// Returns 'Bucket':
+        /// - the change of the payment
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, or by any member of its organisation, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/pay_balance.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/pay_balance.rtm")]
+        /// ```        
+        pub fn pay_balance(&mut self, offer_id: NonFungibleLocalId, customer_badge: NonFungibleLocalId, mut payment: Bucket) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let nfdata = self.check_member(&offer_id, &customer_badge);
+
+            // the funds of an escrow offer are held by the component and released on delivery, never paid here
+            assert!(!nfdata.escrow, "An escrow offer is paid on acceptance, use confirm_delivery to release it!");
+            assert!(nfdata.state == OfferState::Accepted || nfdata.state == OfferState::PartiallyPaid, "Offer is not in progress!");
+            assert!(nfdata.milestones.is_empty(), "A milestone offer is paid by milestone, use pay_milestone!");
+            assert!(nfdata.amount_outstanding > Decimal::ZERO, "Offer has no outstanding amount!");
+            assert!(payment.resource_address() == nfdata.currency, "Payment is not in the currency of the offer!");
+            assert!(payment.amount() >= nfdata.amount_outstanding, "Payment is lower than the outstanding amount of the offer!");
+            let amount = nfdata.amount_outstanding;
+            let actor = self.customer_actor(&customer_badge);
+
+            info!("Paying the balance of an offer for this pdf {:?} with this amount {:?} at time  {:?} ",nfdata.hash_pdf, amount, now);
+
+            self.deposit_to_treasury(payment.take(amount));
+            self.record_payment(&offer_id, &nfdata, amount);
+            let nfdata = self.transition(&offer_id, OfferState::FullyPaid, actor.clone());
+
+            //emit the event
+            Runtime::emit_event(PaidOfferEvent { offer_id: offer_id, offer: nfdata, amount: amount, actor: actor, timestamp: now});
+
+            payment
+        }
+
+        /// This is for marking as delivered a milestone of an accepted offer
+        /// 
+        /// The Invoice NFT of the milestone is kept by the component until the customer claims it with claim_invoices
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `index`: position of the milestone in the offer
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by an Admin or by a Manager.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/mark_milestone_delivered.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/mark_milestone_delivered.rtm")]
+        /// ```        
+        pub fn mark_milestone_delivered(&mut self, offer_id: NonFungibleLocalId, index: u32, manager_proof: Option<NonFungibleProof>) {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(&offer_id);
+            assert!(nfdata.state == OfferState::Accepted || nfdata.state == OfferState::PartiallyPaid, "Offer is not in progress!");
+
+            let mut milestones = nfdata.milestones.clone();
+            let milestone = milestones.get_mut(index as usize).expect("Milestone not found!");
+            assert!(milestone.delivered_timestamp.is_none(), "Milestone has already been delivered!");
+            milestone.delivered_timestamp = Some(now);
+            let milestone = milestone.clone();
+
+            info!("Delivered milestone {:?} of an offer for this pdf {:?} at time  {:?} ",index, nfdata.hash_pdf, now);
+
+            self.nft_manager.update_non_fungible_data(&offer_id, "milestones", milestones);
+
+            // the invoice is kept for the customer, so that it cannot stay with the manager
+            let invoice = self.mint_invoice(&offer_id, &nfdata, Some(index), milestone.amount, milestone.due_date, actor.clone());
+            let has_vault = self.unclaimed_invoices.get(&offer_id).is_some();
+            if has_vault {
+                self.unclaimed_invoices.get_mut(&offer_id).unwrap().put(invoice.as_non_fungible());
+            } else {
+                self.unclaimed_invoices.insert(offer_id.clone(), NonFungibleVault::with_bucket(invoice.as_non_fungible()));
+            }
+
+            //emit the event
+            Runtime::emit_event(MilestoneDeliveredEvent { offer_id: offer_id, index: index, milestone: milestone, actor: actor, timestamp: now});
+        }
+
+        /// This is for paying a delivered milestone of an accepted offer
+        /// 
+        /// The payment has to be in the currency of the offer and at least the amount of the milestone,
+        /// the amount of the milestone is kept in the treasury of the component and the rest is given back.
+        /// The offer is FULLY_PAID once every milestone has been paid, PARTIALLY_PAID otherwise
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge of a signer of the offer (of a member of the organisation, for an organisation offer)
+        /// - `index`: position of the milestone in the offer
+        /// - `payment`: Bucket with the payment of the milestone
+        ///
+        /// Returns 'Bucket':
+        /// - the change of the payment
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, or by any member of its organisation, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/pay_milestone.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/pay_milestone.rtm")]
+        /// ```        
+        pub fn pay_milestone(&mut self, offer_id: NonFungibleLocalId, customer_badge: NonFungibleLocalId, index: u32, mut payment: Bucket) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let nfdata = self.check_member(&offer_id, &customer_badge);
+            assert!(nfdata.state == OfferState::Accepted || nfdata.state == OfferState::PartiallyPaid, "Offer is not in progress!");
+
+            let mut milestones = nfdata.milestones.clone();
+            let milestone = milestones.get_mut(index as usize).expect("Milestone not found!");
+            assert!(milestone.delivered_timestamp.is_some(), "Milestone has not been delivered yet!");
+            assert!(milestone.paid_timestamp.is_none(), "Milestone has already been paid!");
+            milestone.paid_timestamp = Some(now);
+            let milestone = milestone.clone();
+            let amount = milestone.amount;
+            let actor = self.customer_actor(&customer_badge);
+
+            assert!(payment.resource_address() == nfdata.currency, "Payment is not in the currency of the offer!");
+            assert!(payment.amount() >= amount, "Payment is lower than the amount of the milestone!");
+
+            info!("Paying milestone {:?} of an offer for this pdf {:?} with this amount {:?} at time  {:?} ",index, nfdata.hash_pdf, amount, now);
+
+            self.deposit_to_treasury(payment.take(amount));
+            self.nft_manager.update_non_fungible_data(&offer_id, "milestones", milestones);
+            let amount_outstanding = self.record_payment(&offer_id, &nfdata, amount);
+            if amount_outstanding == Decimal::ZERO {
+                self.transition(&offer_id, OfferState::FullyPaid, actor.clone());
+            } else if nfdata.state == OfferState::Accepted {
+                self.transition(&offer_id, OfferState::PartiallyPaid, actor.clone());
+            }
+
+            //emit the event
+            Runtime::emit_event(MilestonePaidEvent { offer_id: offer_id, index: index, milestone: milestone, amount: amount, actor: actor, timestamp: now});
+
+            payment
+        }
+
+        /// This is for claiming the Invoice NFTs of the delivered milestones of an offer
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge of a signer of the offer (of a member of the organisation, for an organisation offer)
+        ///
+        /// Returns 'Bucket':
+        /// - the Invoice NFTs not claimed yet
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, or by any member of its organisation, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/claim_invoices.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/claim_invoices.rtm")]
+        /// ```        
+        pub fn claim_invoices(&mut self, offer_id: NonFungibleLocalId, customer_badge: NonFungibleLocalId) -> Bucket {
+            self.check_member(&offer_id, &customer_badge);
+
+            info!("Claiming the invoices of offer {:?} with badge {:?} ", offer_id, customer_badge);
+
+            self.unclaimed_invoices
+                .get_mut(&offer_id)
+                .expect("No invoices to be claimed for this offer!")
+                .take_all()
+                .into()
+        }
+
+        /// This is for marking as delivered an escrow offer that has been paid
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by an Admin or by a Manager.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/mark_delivered.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/mark_delivered.rtm")]
+        /// ```        
+        pub fn mark_delivered(&mut self, offer_id: NonFungibleLocalId, manager_proof: Option<NonFungibleProof>) {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+
+            let nfdata = self.transition(&offer_id, OfferState::Delivered, actor.clone());
+
+            info!("Delivered an offer for this pdf {:?} at time  {:?} ",nfdata.hash_pdf, now);
+
+            //emit the event
+            Runtime::emit_event(DeliveredOfferEvent { offer_id: offer_id, offer: nfdata, actor: actor, timestamp: now});
+        }
+
+        /// This is for confirming the delivery of an escrow offer, the escrowed payment is released to the treasury
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge of a signer of the offer (of an approver of the organisation, for an organisation offer)
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, or by an approver of its organisation, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/confirm_delivery.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/confirm_delivery.rtm")]
+        /// ```        
+        pub fn confirm_delivery(&mut self, offer_id: NonFungibleLocalId, customer_badge: NonFungibleLocalId) {
+            // releasing the escrow is a decision on the offer, a viewer of the organisation cannot take it
+            self.check_customer(&offer_id, &customer_badge);
+            let actor = self.customer_actor(&customer_badge);
+            self.release_escrow_funds(&offer_id, false, actor);
+        }
+
+        /// This is for releasing the escrowed payment of a delivered offer when the customer has not confirmed the delivery
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone, once the escrow timeout has passed since the delivery.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/release_escrow.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/release_escrow.rtm")]
+        /// ```        
+        pub fn release_escrow(&mut self, offer_id: NonFungibleLocalId) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(&offer_id);
+            let release_time = nfdata.delivered_timestamp
+                .expect("Offer has not been delivered!")
+                .add_seconds(self.escrow_timeout)
+                .unwrap();
+            assert!(now.compare(release_time, TimeComparisonOperator::Gte), "Escrow timeout has not passed yet!");
+
+            self.release_escrow_funds(&offer_id, true, None);
+        }
+
+        /// This is for refusing an offer
+        /// 
+        /// The refusal of a signer refuses the offer if `refusal_by_signer` has been set on the offer, 
+        /// otherwise only when the approval threshold cannot be reached anymore; the reason and the note of every 
+        /// refusal are given in the OfferApprovalEvent, even when the offer stays open
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of the signer
+        /// - `reason`: why the offer is refused (price, timing, competitor, scope, other)
+        /// - `note_hash`: Hash of an optional note explaining the refusal
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/refuse_offer.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/refuse_offer.rtm")]
+        /// ```      
+        pub fn refuse_offer(&mut self, offer_id: NonFungibleLocalId, signer_badge: NonFungibleLocalId, reason: RefusalReason, note_hash: Option<String>) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            // Update the state of the Offer
+            let nfdata = self.check_customer(&offer_id, &signer_badge);
+
+            info!("Refuse an offer for this pdf {:?} with this expiry date {:?} at time  {:?} for this reason {:?} ",nfdata.hash_pdf, nfdata.expiry_date, now, reason);
+
+            assert!(now.compare(nfdata.expiry_date, TimeComparisonOperator::Lte), "Offer is expired!");
+            self.check_signer(&nfdata, &signer_badge);
+            let actor = self.customer_actor(&signer_badge);
+
+            let mut refusals = nfdata.refusals.clone();
+            refusals.push(signer_badge.clone());
+            self.nft_manager.update_non_fungible_data(&offer_id, "refusals", refusals.clone());
+
+            //emit the event, the reason is kept even if the refusal does not close the offer
+            Runtime::emit_event(OfferApprovalEvent { offer_id: offer_id.clone(), signer_badge: signer_badge, approved: false, reason: Some(reason), note_hash: note_hash.clone(), approvals: nfdata.approvals.len() as u32, threshold: nfdata.approval_threshold, actor: actor.clone(), timestamp: now});
+
+            // the offer is still open while the other signers can reach the approval threshold
+            let eligible_signers = self.eligible_signers(&nfdata).len();
+            if !nfdata.refusal_by_signer && eligible_signers.saturating_sub(refusals.len()) >= nfdata.approval_threshold as usize {
+                info!("Refusal recorded, the approval threshold can still be reached ");
+                return;
+            }
+
+            self.nft_manager.update_non_fungible_data(&offer_id, "refusal_reason", Some(reason));
+            self.nft_manager.update_non_fungible_data(&offer_id, "refusal_note_hash", note_hash.clone());
+            let nfdata = self.transition(&offer_id, OfferState::Refused, actor.clone());
+
+            // Keep the count of the refusals for the sales reporting
+            let count = self.refusal_counts.get(&reason).map(|count| *count).unwrap_or(0);
+            self.refusal_counts.insert(reason, count + 1);
+
+            //emit the event
+            Runtime::emit_event(RefusedOfferEvent { offer_id: offer_id, offer: nfdata, reason: reason, note_hash: note_hash, actor: actor, timestamp: now});
+        }
+
+        /// This is for sending a counter-proposal for an offer
+        /// 
+        /// The offer is moved to the COUNTERED state and the counter-proposal is kept by the component 
+        /// until a manager accepts it (a new offer is created) or rejects it (the offer is NEW again)
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of a signer of the offer
+        /// - `new_amount`: Amount proposed by the customer
+        /// - `new_hash_pdf`: Hash of the PDF document with the conditions proposed by the customer
+        /// - `note_hash`: Hash of a note explaining the counter-proposal
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called only by a signer of the offer, with its Customer Badge.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/counter_offer.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/counter_offer.rtm")]
+        /// ```      
+        pub fn counter_offer(&mut self, offer_id: NonFungibleLocalId, signer_badge: NonFungibleLocalId, new_amount: Decimal, new_hash_pdf: String, note_hash: String) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            // Update the state of the Offer
+            let nfdata = self.check_customer(&offer_id, &signer_badge);
+
+            info!("Counter an offer for this pdf {:?} with this expiry date {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, now);
+
+            assert!(now.compare(nfdata.expiry_date, TimeComparisonOperator::Lte), "Offer is expired!");
+            assert!(new_amount > Decimal::ZERO, "Amount must be positive!");
+            let actor = self.customer_actor(&signer_badge);
+            self.transition(&offer_id, OfferState::Countered, actor.clone());
+
+            let counter = CounterProposal {
+                offer_amount: new_amount,
+                hash_pdf: new_hash_pdf,
+                note_hash: note_hash,
+                customer_badge: nfdata.customer_badge,
+                create_timestamp: now,
+            };
+            self.counter_offers.insert(offer_id.clone(), counter.clone());
+
+            //emit the event
+            Runtime::emit_event(CounterOfferEvent { offer_id: offer_id, counter: counter, actor: actor, timestamp: now});
+        }
+
+        /// This is for accepting a counter-proposal sent by a customer
+        /// 
+        /// A new OfferData NFT is created with the conditions of the counter-proposal and a reference 
+        /// to the countered offer, the customer can then accept/refuse it as any other offer.
+        /// The countered offer is closed in the SUPERSEDED state
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the countered offer
+        /// - `expiry_date`: Expiry date of the new offer (UTC seconds)
+        /// - `manager_proof`: Proof of the Manager Badge, the new offer has to be within the limits of the manager and is issued by it (none if accepted by an Admin)
+        ///
+        /// Returns 'Bucket':
+        /// - the new OfferData NFT (empty if it is above the approval limit, it is kept by the component until approve_offer)
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by an Admin or by a Manager.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/accept_counter.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/accept_counter.rtm")]
+        /// ```      
+        pub fn accept_counter(&mut self, offer_id: NonFungibleLocalId, expiry_date: Instant, manager_proof: Option<NonFungibleProof>) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let counter = self.counter_offers.remove(&offer_id).expect("No pending counter-proposal for this offer!");
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(&offer_id);
+
+            info!("Accepting a counter-proposal for this pdf {:?} with this amount {:?} at time  {:?} ",counter.hash_pdf, counter.offer_amount, now);
+
+            // the new offer has a single line with the amount proposed by the customer, and no milestones
+            let lines = vec![OfferLine {
+                sku: "COUNTER".to_string(),
+                description_hash: counter.note_hash.clone(),
+                quantity: Decimal::ONE,
+                unit_price: counter.offer_amount,
+                discount: Decimal::ZERO,
+            }];
+            let issued_by = self.check_manager_limits(manager_proof, &lines, nfdata.currency);
+            let quorum = QuorumTerms {
+                co_signers: nfdata.signers[1..].to_vec(),
+                threshold: nfdata.approval_threshold,
+                refusal_by_signer: nfdata.refusal_by_signer,
+            };
+            self.transition(&offer_id, OfferState::Superseded, self.manager_actor(issued_by.clone()));
+            let (new_offer_id, nft) = self.mint_offer(counter.hash_pdf.clone(), expiry_date, lines, nfdata.currency, nfdata.escrow, nfdata.deposit_ratio, vec![], counter.customer_badge.clone(), Some(quorum), issued_by.clone(), Some(offer_id.clone()));
+
+            //emit the event
+            Runtime::emit_event(CounterAcceptedEvent { offer_id: offer_id, new_offer_id: new_offer_id, counter: counter, actor: self.manager_actor(issued_by), timestamp: now});
+
+            nft
+        }
+
+        /// This is for rejecting a counter-proposal sent by a customer
+        /// 
+        /// The countered offer goes back to the NEW state and can still be accepted/refused until its expiry date
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the countered offer
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'None':
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by an Admin or by a Manager.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/reject_counter.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/reject_counter.rtm")]
+        /// ```      
+        pub fn reject_counter(&mut self, offer_id: NonFungibleLocalId, manager_proof: Option<NonFungibleProof>) {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+
+            let counter = self.counter_offers.remove(&offer_id).expect("No pending counter-proposal for this offer!");
+
+            info!("Rejecting a counter-proposal for this pdf {:?} with this amount {:?} at time  {:?} ",counter.hash_pdf, counter.offer_amount, now);
+
+            self.transition(&offer_id, OfferState::New, actor.clone());
+
+            //emit the event
+            Runtime::emit_event(CounterRejectedEvent { offer_id: offer_id, counter: counter, actor: actor, timestamp: now});
+        }
+
+        /// This is for expiring the offers that have passed their expiry date
+        /// 
+        /// Open offers are walked in order of expiry date, the ones past their expiry date
+        /// are moved to the EXPIRED state and an ExpiredOfferEvent is emitted for each of them
+        /// 
+        /// Arguments:
+        /// - `limit`: maximum number of offers to be expired in this call
+        ///
+        /// Returns 'Vec<NonFungibleLocalId>':
+        /// - the list of the expired offers
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        /// **Transaction manifest:**
+        /// `fcgsales/expire_offers.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/expire_offers.rtm")]
+        /// ```      
+        pub fn expire_offers(&mut self, limit: u32) -> Vec<NonFungibleLocalId> {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let expired: Vec<NonFungibleLocalId> = self.open_offers
+                .range(..)
+                .take_while(|(key, _, _)| key.0 < now.seconds_since_unix_epoch)
+                .take(limit as usize)
+                .map(|(_, offer_id, _)| offer_id)
+                .collect();
+
+            for offer_id in expired.iter() {
+                let nfdata = self.transition(offer_id, OfferState::Expired, None);
+
+                info!("Expired an offer for this pdf {:?} with this expiry date {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, now);
+
+                //emit the event
+                Runtime::emit_event(ExpiredOfferEvent { offer_id: offer_id.clone(), offer: nfdata, actor: None, timestamp: now});
+            }
+
+            expired
+        }
+
+        /// This returns the previous versions of an amended offer
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        ///
+        /// Returns 'Vec<OfferVersion>':
+        /// - the previous versions of the offer, the oldest first
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn get_offer_history(&self, offer_id: NonFungibleLocalId) -> Vec<OfferVersion> {
+            self.offer_history.get(&offer_id).map(|history| history.to_vec()).unwrap_or_default()
+        }
+
+        /// This is for finding an offer by its offer number (e.g. OFF-2026-000123)
+        /// 
+        /// Arguments:
+        /// - `offer_number`: the offer number printed on the offer
+        ///
+        /// Returns 'Option<NonFungibleLocalId>':
+        /// - the id of the OfferData NFT, if any
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn get_offer_by_number(&self, offer_number: String) -> Option<NonFungibleLocalId> {
+            self.offers_by_number.get(&offer_number).map(|offer_id| offer_id.clone())
+        }
+
+        /// This is for listing the offers sent to a customer, the oldest first
+        /// 
+        /// Arguments:
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers returned
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the offers
+        /// - the cursor of the next page, if any
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn offers_by_customer(&self, customer_badge: NonFungibleLocalId, cursor: Option<u64>, limit: u32) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            Self::list_index(&self.offers_by_customer, Self::badge_key(&customer_badge), cursor, limit)
+        }
+
+        /// This is for listing the offers sent by a manager, the oldest first
+        /// 
+        /// Arguments:
+        /// - `manager_badge`: NonFungibleLocalId of the Manager Badge
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers returned
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the offers
+        /// - the cursor of the next page, if any
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn offers_by_manager(&self, manager_badge: NonFungibleLocalId, cursor: Option<u64>, limit: u32) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            Self::list_index(&self.offers_by_manager, Self::badge_key(&manager_badge), cursor, limit)
+        }
+
+        /// This is for listing the offers in a state, the oldest first
+        /// 
+        /// Arguments:
+        /// - `state`: the state of the offers
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers returned
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the offers
+        /// - the cursor of the next page, if any
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn offers_by_state(&self, state: OfferState, cursor: Option<u64>, limit: u32) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            Self::list_index(&self.offers_by_state, state, cursor, limit)
+        }
+
+        /// This is for listing the open offers (NEW state) expiring before a date, in order of expiry date
+        /// 
+        /// Arguments:
+        /// - `before`: the date the offers expire before
+        /// - `limit`: the maximum number of offers returned
+        ///
+        /// Returns 'Vec<(Instant, NonFungibleLocalId)>':
+        /// - the expiry date and the id of the offers
+        ///
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///
+        pub fn open_offers_expiring_before(&self, before: Instant, limit: u32) -> Vec<(Instant, NonFungibleLocalId)> {
+            self.open_offers
+                .range(..)
+                .take_while(|(key, _, _)| key.0 < before.seconds_since_unix_epoch)
+                .take(limit as usize)
+                .map(|(key, offer_id, _)| (Instant::new(key.0), offer_id))
+                .collect()
+        }
+
+        /// Utility function: Set the prefix of the offer numbers
+        /// 
+        /// Arguments:
+        /// - `prefix`: the prefix (e.g. OFF)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_offer_number_prefix(&mut self, prefix: String) {
+            assert!(!prefix.is_empty(), "Prefix cannot be empty!");
+            info!("Setting offer number prefix to {:?} ", prefix);
+            self.offer_number_prefix = prefix;
+        }
+
+        /// Utility function: Choose whether a cancelled offer is burned or kept by the component
+        /// 
+        /// Arguments:
+        /// - `burn`: if true the OfferData NFT recalled by `cancel_offer` is burned
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_burn_on_cancel(&mut self, burn: bool) {
+            info!("Setting burn on cancel to {:?} ", burn);
+            self.burn_on_cancel = burn;
+        }
+
+        /// Utility function: Add a currency to the ones an offer can be denominated in
+        /// 
+        /// Arguments:
+        /// - `currency`: a fungible resource (e.g. XRD or a stablecoin)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn add_currency(&mut self, currency: ResourceAddress) {
+            assert!(ResourceManager::from(currency).resource_type().is_fungible(), "A currency has to be a fungible resource!");
+            assert!(!self.currencies.contains(&currency), "Currency is already accepted!");
+            info!("Adding currency {:?} ", currency);
+            self.currencies.push(currency);
+        }
+
+        /// Utility function: Remove a currency from the ones an offer can be denominated in
+        /// 
+        /// Offers already sent in this currency are not changed
+        /// 
+        /// Arguments:
+        /// - `currency`: the currency to be removed
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn remove_currency(&mut self, currency: ResourceAddress) {
+            let position = self.currencies.iter().position(|accepted| *accepted == currency).expect("Currency is not accepted!");
+            info!("Removing currency {:?} ", currency);
+            self.currencies.remove(position);
+        }
+
+        /// Utility function: Get the currencies an offer can be denominated in
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn get_currencies(&self) -> Vec<ResourceAddress> {
+            self.currencies.clone()
+        }
+
+        /// Utility function: Get the number of offers refused, by reason
+        /// 
+        /// Returns 'Vec<(RefusalReason, u64)>':
+        /// - the number of refusals for each reason
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn get_refusal_counts(&self) -> Vec<(RefusalReason, u64)> {
+            RefusalReason::ALL
+                .iter()
+                .map(|reason| (*reason, self.refusal_counts.get(reason).map(|count| *count).unwrap_or(0)))
+                .collect()
+        }
+
+        /// Utility function: Withdraw the payments received for the offers
+        /// 
+        /// Arguments:
+        /// - `currency`: currency to be withdrawn
+        /// - `amount`: amount to be withdrawn
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn withdraw_treasury(&mut self, currency: ResourceAddress, amount: Decimal) -> Bucket {
+            info!("Withdrawing {:?} of currency {:?} from the treasury ", amount, currency);
+            self.treasury
+                .get_mut(&currency)
+                .expect("No payments received in this currency!")
+                .take(amount)
+        }
+
+        /// Utility function: Set the seconds after delivery when escrowed funds can be released without the customer
+        /// 
+        /// Arguments:
+        /// - `seconds`: the escrow timeout
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_escrow_timeout(&mut self, seconds: i64) {
+            assert!(seconds >= 0, "Escrow timeout cannot be negative!");
+            info!("Setting escrow timeout to {:?} seconds ", seconds);
+            self.escrow_timeout = seconds;
+        }
+
+        /// Utility function: Set the tax applied to the amount of an invoice
+        /// 
+        /// Arguments:
+        /// - `rate`: the tax rate (e.g. 0.22 for 22%)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_tax_rate(&mut self, rate: Decimal) {
+            assert!(rate >= Decimal::ZERO, "Tax rate cannot be negative!");
+            info!("Setting tax rate to {:?} ", rate);
+            self.tax_rate = rate;
+        }
+
+        /// Utility function: Set the seconds after issue when an invoice is due
+        /// 
+        /// Arguments:
+        /// - `seconds`: the payment terms
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_payment_terms(&mut self, seconds: i64) {
+            assert!(seconds >= 0, "Payment terms cannot be negative!");
+            info!("Setting payment terms to {:?} seconds ", seconds);
+            self.payment_terms = seconds;
+        }
+
+        /// Utility function: Set the amount above which an offer needs an internal approval before being sent
+        /// 
+        /// Arguments:
+        /// - `currency`: the currency of the offers
+        /// - `limit`: the approval limit (none to remove it)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_approval_limit(&mut self, currency: ResourceAddress, limit: Option<Decimal>) {
+            info!("Setting approval limit for {:?} to {:?} ", currency, limit);
+            match limit {
+                Some(limit) => {
+                    assert!(limit >= Decimal::ZERO, "Approval limit cannot be negative!");
+                    self.approval_limits.insert(currency, limit);
+                }
+                None => {
+                    self.approval_limits.remove(&currency);
+                }
+            }
+        }
+
+        /// Utility function: Allow a manager to approve the offers above the approval limit
+        /// 
+        /// Arguments:
+        /// - `manager_id`: NonFungibleLocalId of the Manager Badge
+        /// - `senior`: if true the manager can approve/reject offers internally
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_senior_manager(&mut self, manager_id: NonFungibleLocalId, senior: bool) {
+            info!("Setting manager {:?} as senior {:?} ", manager_id, senior);
+            self.manager_badge_resource_manager.update_non_fungible_data(&manager_id, "senior", senior);
+
+            //emit the event
+            self.emit_manager_updated(manager_id);
+        }
+
+        /// Utility function: Set the limits of a manager when sending an offer
+        /// 
+        /// Arguments:
+        /// - `manager_id`: NonFungibleLocalId of the Manager Badge
+        /// - `max_offer_amount`: the maximum amount of an offer, not negative (none for no limit)
+        /// - `max_discount`: the maximum discount of a line of an offer, between 0 and 1 (none for no limit)
+        /// - `allowed_currencies`: the currencies the manager can send offers in (empty for all the accepted currencies)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn set_manager_limits(&mut self, manager_id: NonFungibleLocalId, max_offer_amount: Option<Decimal>, max_discount: Option<Decimal>, allowed_currencies: Vec<ResourceAddress>) {
+            assert!(max_offer_amount.map_or(true, |amount| amount >= Decimal::ZERO), "Maximum offer amount cannot be negative!");
+            assert!(max_discount.map_or(true, |discount| discount >= Decimal::ZERO && discount <= Decimal::ONE), "Maximum discount must be between 0 and 1!");
+            info!("Setting limits of manager {:?}: amount {:?}, discount {:?}, currencies {:?} ", manager_id, max_offer_amount, max_discount, allowed_currencies);
+            self.manager_badge_resource_manager.update_non_fungible_data(&manager_id, "max_offer_amount", max_offer_amount);
+            self.manager_badge_resource_manager.update_non_fungible_data(&manager_id, "max_discount", max_discount);
+            self.manager_badge_resource_manager.update_non_fungible_data(&manager_id, "allowed_currencies", allowed_currencies);
+
+            //emit the event
+            self.emit_manager_updated(manager_id);
+        }
+
+        /// Utility function: Get the invoices issued for an offer
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn get_invoices(&self, offer_id: NonFungibleLocalId) -> Vec<NonFungibleLocalId> {
+            self.invoices.get(&offer_id).map(|invoices| invoices.to_vec()).unwrap_or_default()
+        }
+
+        /// Utility function: Mint a manager badge
+        /// 
+        /// Arguments:
+        /// - `username`: Username that will be registered in the NFT
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        pub fn mint_manager_badge(&mut self, username: String) -> Bucket {
+            assert!(self.manager_usernames.get(&username).is_none(), "Username is already taken by a manager!");
+            // badge ids come from a counter, so the id of a revoked badge is never reused
+            self.manager_counter += 1;
+            let key = self.manager_counter;
+            let manager_badge_bucket: Bucket = self
+                .manager_badge_resource_manager
+                .mint_non_fungible(
+                    &NonFungibleLocalId::integer(key),
+                    ManagerBadge {
+                        username: username.clone(),
+                        senior: false,
+                        max_offer_amount: None,
+                        max_discount: None,
+                        allowed_currencies: vec![],
+                    });
+
+            let id = manager_badge_bucket.as_non_fungible().non_fungible_local_id();
+            info!("Saving staff badge with key : {:?} and id {:?} for the username: {:?}  ",key, id, username);
+            self.manager.insert(key, username.clone());
+            self.manager_usernames.insert(username.clone(), id.clone());
+
+            //emit the event
+            Runtime::emit_event(BadgeMintedEvent { 
+                badge_resource: self.manager_badge_resource_manager.address(), 
+                badge_id: id, 
+                username: username, 
+                actor: None, 
+                timestamp: Clock::current_time_rounded_to_seconds()
+            });
+
+            manager_badge_bucket
         }
 
         /// Utility function: Mint a customer badge
         /// 
         /// Arguments:
-        /// - `username`: Username that will be registered in the NFT
+        /// - `username`: Username that will be registered in the NFT
+        /// - `organisation`: Id of the customer organisation of the member (none for a single customer)
+        /// - `role`: Role of the member within its organisation (viewer or approver)
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Admin or the Customer only.
+        ///                    
+        pub fn mint_customer_badge(&mut self, username: String, organisation: Option<u64>, role: CustomerRole, manager_proof: Option<NonFungibleProof>) -> Bucket {
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+            assert!(self.customer_usernames.get(&username).is_none(), "Username is already taken by a customer!");
+            // badge ids come from a counter, so the id of a revoked badge is never reused
+            self.customer_counter += 1;
+            let key = self.customer_counter;
+            let customer_badge_bucket: Bucket = self
+                .customer_badge_resource_manager
+                .mint_non_fungible(
+                    &NonFungibleLocalId::integer(key),
+                    CustomerBadge {
+                        username: username.clone(),
+                        organisation: organisation,
+                        role: role,
+                    });
+
+            let id = customer_badge_bucket.as_non_fungible().non_fungible_local_id();
+            info!("Saving staff badge with key : {:?} and id {:?} for the username: {:?}  ",key, id, username);
+            self.customer.insert(key, username.clone());
+            self.customer_usernames.insert(username.clone(), id.clone());
+
+            if let Some(organisation) = organisation {
+                let mut org = self.organisations.get_mut(&organisation).expect("Organisation not found!");
+                org.members.push(id.clone());
+                if role == CustomerRole::Approver {
+                    org.approvers.push(id.clone());
+                }
+            }
+
+            //emit the event
+            Runtime::emit_event(BadgeMintedEvent { 
+                badge_resource: self.customer_badge_resource_manager.address(), 
+                badge_id: id, 
+                username: username, 
+                actor: actor, 
+                timestamp: Clock::current_time_rounded_to_seconds()
+            });
+
+            customer_badge_bucket
+        }
+
+        /// Utility function: Create a customer organisation
+        /// 
+        /// Arguments:
+        /// - `legal_name_hash`: Hash of the legal name of the organisation
+        /// - `vat_number_hash`: Hash of the VAT number of the organisation
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'u64':
+        /// - the id of the organisation, to be referenced by the customer badges of its members
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Admin or a Manager only.
+        ///                    
+        pub fn create_organisation(&mut self, legal_name_hash: String, vat_number_hash: String, manager_proof: Option<NonFungibleProof>) -> u64 {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+
+            self.organisation_counter += 1;
+            info!("Creating organisation {:?} with legal name hash {:?} ", self.organisation_counter, legal_name_hash);
+            let organisation = CustomerOrganisation {
+                legal_name_hash: legal_name_hash,
+                vat_number_hash: vat_number_hash,
+                create_timestamp: now,
+                members: vec![],
+                approvers: vec![],
+            };
+            self.organisations.insert(self.organisation_counter, organisation.clone());
+
+            //emit the event
+            Runtime::emit_event(OrganisationCreatedEvent { organisation_id: self.organisation_counter, organisation: organisation, actor: actor, timestamp: now});
+
+            self.organisation_counter
+        }
+
+        /// Utility function: Get a customer organisation
+        /// 
+        /// Arguments:
+        /// - `organisation`: Id of the organisation
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn get_organisation(&self, organisation: u64) -> Option<CustomerOrganisation> {
+            self.organisations.get(&organisation).map(|org| org.clone())
+        }
+
+        /// Utility function: Change the role of a customer member within its organisation
+        /// 
+        /// Arguments:
+        /// - `local_id`: NonFungibleLocalId of the Customer Badge
+        /// - `role`: the new role (viewer or approver)
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Admin or a Manager only.
+        ///                    
+        pub fn set_customer_role(&mut self, local_id: NonFungibleLocalId, role: CustomerRole, manager_proof: Option<NonFungibleProof>) {
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+            let customer: CustomerBadge = self.customer_badge_resource_manager.get_non_fungible_data(&local_id);
+            info!("Setting role of customer {:?} to {:?} ", customer.username, role);
+            self.customer_badge_resource_manager.update_non_fungible_data(&local_id, "role", role);
+
+            if let Some(organisation) = customer.organisation {
+                let mut org = self.organisations.get_mut(&organisation).expect("Organisation not found!");
+                org.approvers.retain(|approver| *approver != local_id);
+                if role == CustomerRole::Approver {
+                    org.approvers.push(local_id.clone());
+                }
+            }
+
+            //emit the event
+            Runtime::emit_event(CustomerRoleChangedEvent { badge_id: local_id, from: customer.role, to: role, actor: actor, timestamp: Clock::current_time_rounded_to_seconds()});
+        }
+
+        /// Utility function: List the manager members, by badge id
+        /// 
+        /// Arguments:
+        /// - `cursor`: the badge id to start from (none from the first one)
+        /// - `limit`: the maximum number of members returned
+        ///
+        /// Returns '(Vec<(NonFungibleLocalId, String)>, Option<u64>)':
+        /// - the badge id and the username of the members
+        /// - the cursor of the next page, if any
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn list_managers(&self, cursor: Option<u64>, limit: u32) -> (Vec<(NonFungibleLocalId, String)>, Option<u64>) {
+            Self::list_registry(&self.manager, cursor, limit)
+        }
+
+        /// Utility function: List the customer members, by badge id
+        /// 
+        /// Arguments:
+        /// - `cursor`: the badge id to start from (none from the first one)
+        /// - `limit`: the maximum number of members returned
+        ///
+        /// Returns '(Vec<(NonFungibleLocalId, String)>, Option<u64>)':
+        /// - the badge id and the username of the members
+        /// - the cursor of the next page, if any
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn list_customers(&self, cursor: Option<u64>, limit: u32) -> (Vec<(NonFungibleLocalId, String)>, Option<u64>) {
+            Self::list_registry(&self.customer, cursor, limit)
+        }
+
+        /// Utility function: Find the badge of a manager by username
+        /// 
+        /// Arguments:
+        /// - `username`: Username registered in the badge
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn find_manager(&self, username: String) -> Option<NonFungibleLocalId> {
+            self.manager_usernames.get(&username).map(|id| id.clone())
+        }
+
+        /// Utility function: Find the badge of a customer by username
+        /// 
+        /// Arguments:
+        /// - `username`: Username registered in the badge
+        /// ---
+        ///
+        /// **Access control:** Can be called by anyone.
+        ///                    
+        pub fn find_customer(&self, username: String) -> Option<NonFungibleLocalId> {
+            self.customer_usernames.get(&username).map(|id| id.clone())
+        }
+
+        /// Utility function: Revoke a manager badge
+        /// 
+        /// The badge is recalled from the vault holding it, burned and removed from the list of managers
+        /// 
+        /// Arguments:
+        /// - `local_id`: NonFungibleLocalId of the Manager Badge
+        /// - `badge_vault`: Address of the vault holding the badge (e.g. in the manager account)
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Owner or the Admin only.
+        ///                    
+        /// **Transaction manifest:**
+        /// `fcgsales/revoke_manager.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/revoke_manager.rtm")]
+        /// ```    
+        pub fn revoke_manager(&mut self, local_id: NonFungibleLocalId, badge_vault: InternalAddress) {
+            let now = Clock::current_time_rounded_to_seconds();
+            let manager: ManagerBadge = self.manager_badge_resource_manager.get_non_fungible_data(&local_id);
+
+            info!("Revoking manager badge {:?} of the username: {:?}  ",local_id, manager.username);
+
+            Self::recall_non_fungible(badge_vault, self.manager_badge_resource_manager.address(), &local_id).burn();
+            Self::remove_from_registry(&mut self.manager, &local_id);
+            self.manager_usernames.remove(&manager.username);
+
+            //emit the event
+            Runtime::emit_event(BadgeRevokedEvent { 
+                badge_resource: self.manager_badge_resource_manager.address(), 
+                badge_id: local_id, 
+                username: manager.username, 
+                cancelled_offers: vec![], 
+                actor: None, 
+                timestamp: now
+            });
+        }
+
+        /// Utility function: Revoke a customer badge
+        /// 
+        /// The badge is recalled from the vault holding it, burned and removed from the list of customers,
+        /// the open offers sent to the customer can be cancelled together, a page at a time 
+        /// (the following pages are cancelled with cancel_customer_offers)
+        /// 
+        /// Arguments:
+        /// - `local_id`: NonFungibleLocalId of the Customer Badge
+        /// - `badge_vault`: Address of the vault holding the badge (e.g. in the customer account)
+        /// - `cancel_limit`: the maximum number of offers of the customer looked at for cancelling them (0 to keep the offers)
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'Option<u64>':
+        /// - the cursor to continue with cancel_customer_offers, if there are more offers to be looked at
+        /// ---
+        ///
+        /// **Access control:** Can be called by the Admin or a Manager only.
+        ///                    
+        /// **Transaction manifest:**
+        /// `fcgsales/revoke_customer.rtm`
+        /// ```text
+        #[doc = include_str!("../fcgsales/revoke_customer.rtm")]
+        /// ```    
+        pub fn revoke_customer(&mut self, local_id: NonFungibleLocalId, badge_vault: InternalAddress, cancel_limit: u32, manager_proof: Option<NonFungibleProof>) -> Option<u64> {
+            let now = Clock::current_time_rounded_to_seconds();
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+            let customer: CustomerBadge = self.customer_badge_resource_manager.get_non_fungible_data(&local_id);
+
+            info!("Revoking customer badge {:?} of the username: {:?}  ",local_id, customer.username);
+
+            Self::recall_non_fungible(badge_vault, self.customer_badge_resource_manager.address(), &local_id).burn();
+            Self::remove_from_registry(&mut self.customer, &local_id);
+            self.customer_usernames.remove(&customer.username);
+            if let Some(organisation) = customer.organisation {
+                let mut org = self.organisations.get_mut(&organisation).expect("Organisation not found!");
+                org.members.retain(|member| *member != local_id);
+                org.approvers.retain(|approver| *approver != local_id);
+            }
+
+            let (cancelled_offers, next_cursor) = if cancel_limit > 0 {
+                self.cancel_open_offers_of(&local_id, None, cancel_limit, actor.clone())
+            } else {
+                (vec![], None)
+            };
+
+            //emit the event
+            Runtime::emit_event(BadgeRevokedEvent { 
+                badge_resource: self.customer_badge_resource_manager.address(), 
+                badge_id: local_id, 
+                username: customer.username, 
+                cancelled_offers: cancelled_offers, 
+                actor: actor, 
+                timestamp: now
+            });
+
+            next_cursor
+        }
+
+        /// Utility function: Cancel the open offers sent to a customer, e.g. after revoking its badge
+        /// 
+        /// The offers of the customer are looked at a page at a time, the ones still NEW, COUNTERED 
+        /// or PENDING_APPROVAL are cancelled (the OfferData NFT of a NEW offer is left to the customer)
+        /// 
+        /// Arguments:
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers looked at
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the cancelled offers
+        /// - the cursor of the next page, if any
         /// ---
         ///
-        /// **Access control:** Can be called by the Admin or the Customer only.
+        /// **Access control:** Can be called by the Admin or a Manager only.
         ///                    
-        pub fn mint_customer_badge(&mut self, username: String) -> Bucket {
-            let customer_badge_bucket: Bucket = self
-                .customer_badge_resource_manager
-                .mint_non_fungible(
-                    &NonFungibleLocalId::integer((self.customer.get_length()+1).try_into().unwrap()),
-                    CustomerBadge {
-                        username: username.clone(),
-                    });
+        pub fn cancel_customer_offers(&mut self, customer_badge: NonFungibleLocalId, cursor: Option<u64>, limit: u32, manager_proof: Option<NonFungibleProof>) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            let actor = self.manager_actor(self.check_manager(manager_proof));
+            self.cancel_open_offers_of(&customer_badge, cursor, limit, actor)
+        }
 
-            let id = customer_badge_bucket.as_non_fungible().non_fungible_local_id();
-            let key = self.customer.get_length().to_u16().unwrap()+1; 
-            info!("Saving staff badge with key : {:?} and id {:?} for the username: {:?}  ",key, id, username);
-            self.customer.insert(key, id);
+        /// Internal function: Cancel the open offers in a page of the offers sent to a customer
+        /// 
+        /// Arguments:
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers looked at
+        /// - `actor`: global id of the Manager Badge cancelling the offers (none for an Admin)
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the cancelled offers
+        /// - the cursor of the next page, if any
+        ///
+        fn cancel_open_offers_of(&mut self, customer_badge: &NonFungibleLocalId, cursor: Option<u64>, limit: u32, actor: Option<NonFungibleGlobalId>) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            let now = Clock::current_time_rounded_to_seconds();
+            let (offers, next_cursor) = self.offers_by_customer(customer_badge.clone(), cursor, limit);
 
-            customer_badge_bucket
+            let mut cancelled_offers: Vec<NonFungibleLocalId> = vec![];
+            for offer_id in offers {
+                // the state is read from the index: the OfferData NFT of an offer already cancelled may have been burned
+                let sequence = *self.offer_sequence.get(&offer_id).expect("Offer is not indexed!");
+                let state = match [OfferState::New, OfferState::Countered, OfferState::PendingApproval]
+                    .into_iter()
+                    .find(|state| self.offers_by_state.get(&(*state, sequence)).is_some())
+                {
+                    Some(state) => state,
+                    None => continue,
+                };
+                // a pending counter-proposal is dropped together with the offer
+                self.counter_offers.remove(&offer_id);
+                let nfdata = self.transition(&offer_id, OfferState::Cancelled, actor.clone());
+
+                // the OfferData NFT of an offer waiting for an internal approval is still held by the component
+                let burned = state == OfferState::PendingApproval && self.burn_on_cancel;
+                if state == OfferState::PendingApproval {
+                    let nft = self.pending_offers.take_non_fungible(&offer_id);
+                    if burned {
+                        nft.burn();
+                    } else {
+                        self.cancelled_offers.put(nft);
+                    }
+                }
+
+                //emit the event
+                Runtime::emit_event(CancelledOfferEvent { offer_id: offer_id.clone(), offer: nfdata, burned: burned, actor: actor.clone(), timestamp: now});
+                cancelled_offers.push(offer_id);
+            }
+
+            (cancelled_offers, next_cursor)
+        }
+
+        /// Internal function: Mint a new OfferData NFT in the NEW state, 
+        /// or in the PENDING_APPROVAL state if its amount is above the approval limit of its currency
+        /// 
+        /// Arguments:
+        /// - `hash_pdf`: This is the hash of the PDF document that contains the commercial offer 
+        /// - `expiry_date`: Expiry date of the offer (UTC seconds)
+        /// - `lines`: Priced lines of the offer, the amount of the offer is their total
+        /// - `currency`: Resource the amount of the offer is denominated in
+        /// - `escrow`: if true the payment is held by the component until the customer confirms the delivery
+        /// - `deposit_ratio`: Fraction (between 0 and 1) of the amount to be paid on acceptance
+        /// - `milestones`: Payment schedule of a project offer (empty if not needed)
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge that is allowed to accept/refuse this offer
+        /// - `quorum`: Other Customer Badges that have to sign the acceptance and how many approvals are needed
+        /// - `issued_by`: NonFungibleLocalId of the Manager Badge sending the offer (none for an Admin)
+        /// - `parent_offer`: NonFungibleLocalId of the offer this one has been created from, if any
+        ///
+        /// Returns '(NonFungibleLocalId, Bucket)':
+        /// - the id of the new offer
+        /// - the OfferData NFT (empty if it is kept by the component waiting for an internal approval)
+        ///
+        fn mint_offer(&mut self, hash_pdf: String, expiry_date: Instant, lines: Vec<OfferLine>, currency: ResourceAddress, escrow: bool, deposit_ratio: Decimal, milestones: Vec<MilestoneTerms>, customer_badge: NonFungibleLocalId, quorum: Option<QuorumTerms>, issued_by: Option<NonFungibleLocalId>, parent_offer: Option<NonFungibleLocalId>) -> (NonFungibleLocalId, Bucket) {
+            // the offer can only be bound to an existing customer
+            let customer: CustomerBadge = self.customer_badge_resource_manager.get_non_fungible_data(&customer_badge);
+
+            //mint an NFT
+            let now = Clock::current_time_rounded_to_seconds();
+            assert!(now.compare(expiry_date, TimeComparisonOperator::Lt), "Expiry date is already in the past!");
+            assert!(self.currencies.contains(&currency), "Currency is not accepted!");
+            assert!(deposit_ratio >= Decimal::ZERO && deposit_ratio <= Decimal::ONE, "Deposit ratio must be between 0 and 1!");
+            assert!(!escrow || deposit_ratio == Decimal::ONE, "An escrow offer has to be paid in full on acceptance!");
+            let offer_amount = OfferLine::total_of(&lines);
+            let milestones: Vec<Milestone> = milestones.into_iter().map(Milestone::from_terms).collect();
+            if !milestones.is_empty() {
+                assert!(Milestone::total_of(&milestones) == offer_amount, "Milestones have to add up to the amount of the offer!");
+                assert!(!escrow && deposit_ratio == Decimal::ZERO, "A milestone offer is paid by milestone, without escrow or deposit!");
+            }
+            let quorum = quorum.unwrap_or(QuorumTerms { co_signers: vec![], threshold: 1, refusal_by_signer: true });
+            let mut signers = vec![customer_badge.clone()];
+            for co_signer in quorum.co_signers {
+                // every signer has to be an existing customer
+                let _: CustomerBadge = self.customer_badge_resource_manager.get_non_fungible_data(&co_signer);
+                assert!(!signers.contains(&co_signer), "Signers have to be different customers!");
+                signers.push(co_signer);
+            }
+            // an offer to a member of an organisation targets the organisation, its approvers are the signers
+            let eligible_signers = match customer.organisation {
+                Some(organisation) => {
+                    assert!(signers.len() == 1, "The signers of an organisation offer are the approvers of the organisation!");
+                    self.organisations.get(&organisation).expect("Organisation not found!").approvers.len()
+                }
+                None => signers.len(),
+            };
+            assert!(quorum.threshold >= 1 && quorum.threshold as usize <= eligible_signers, "Approval threshold must be between 1 and the number of signers!");
+            let pending_approval = self.approval_limits
+                .get(&currency)
+                .map(|limit| offer_amount > *limit)
+                .unwrap_or(false);
+            let state = if pending_approval { OfferState::PendingApproval } else { OfferState::New };
+            let offer_number = self.next_offer_number(now);
+            let offer = OfferData {
+                offer_number: offer_number.clone(),
+                hash_pdf: hash_pdf.clone(),
+                expiry_date: expiry_date,
+                state: state,    
+                create_timestamp: now,
+                accepted_timestamp: None,
+                refused_timestamp: None,
+                cancelled_timestamp: None,
+                expired_timestamp: None,
+                paid_timestamp: None,
+                delivered_timestamp: None,
+                lines: lines,
+                offer_amount: offer_amount,
+                currency: currency,
+                escrow: escrow,
+                deposit_ratio: deposit_ratio,
+                amount_paid: Decimal::ZERO,
+                amount_outstanding: offer_amount,
+                milestones: milestones,
+                version: 1,
+                refusal_reason: None,
+                refusal_note_hash: None,
+                internal_rejection_hash: None,
+                signers: signers,
+                approval_threshold: quorum.threshold,
+                refusal_by_signer: quorum.refusal_by_signer,
+                approvals: vec![],
+                refusals: vec![],
+                customer_badge: customer_badge.clone(),
+                organisation: customer.organisation,
+                issued_by: issued_by.clone(),
+                parent_offer: parent_offer   
+            };
+            let actor = self.manager_actor(issued_by.clone());
+
+            info!("Minting an offer ");
+            
+            let nft = self
+            .nft_manager
+                .mint_ruid_non_fungible(offer.clone());
+
+            let offer_id = nft.as_non_fungible().non_fungible_local_id();
+            self.offers_by_number.insert(offer_number.clone(), offer_id.clone());
+
+            // Index the offer by customer, by manager and by state
+            self.offer_counter += 1;
+            let sequence = self.offer_counter;
+            self.offer_sequence.insert(offer_id.clone(), sequence);
+            self.offers_by_customer.insert((Self::badge_key(&customer_badge), sequence), offer_id.clone());
+            if let Some(manager_badge) = issued_by {
+                self.offers_by_manager.insert((Self::badge_key(&manager_badge), sequence), offer_id.clone());
+            }
+            self.offers_by_state.insert((state, sequence), offer_id.clone());
+
+            //emit the event, every minted offer is created even if it is not sent yet
+            Runtime::emit_event(CreatedOfferEvent { offer_id: offer_id.clone(), offer: offer.clone(), actor: actor.clone(), timestamp: now});
+
+            // An offer above the approval limit is kept by the component until it is approved
+            if pending_approval {
+                info!("Offer {:?} is waiting for an internal approval ", offer_number);
+                self.pending_offers.put(nft.as_non_fungible());
+                return (offer_id, Bucket::new(self.nft_manager.address()));
+            }
+
+            // Keep track of the offer until it is decided or expired
+            self.open_offers.insert((expiry_date.seconds_since_unix_epoch, offer_id.clone()), offer_id.clone());
+
+            //emit the event
+            Runtime::emit_event(SentOfferEvent { offer_id: offer_id.clone(), offer: offer, actor: actor, timestamp: now});
+
+            info!("Sending offer {:?} for this pdf {:?} with this expiry date {:?} to this customer {:?} with badge {:?}  ",offer_number, hash_pdf, expiry_date, customer.username, customer_badge);
+
+            (offer_id, nft)
+        }
+
+        /// Internal function: Generate the next offer number, the sequence restarts every year
+        /// 
+        /// Arguments:
+        /// - `now`: the current time
+        ///
+        /// Returns 'String':
+        /// - the offer number (e.g. OFF-2026-000123)
+        ///
+        fn next_offer_number(&mut self, now: Instant) -> String {
+            let year = UtcDateTime::from_instant(&now).expect("Invalid current time!").year() as i64;
+            if year != self.offer_number_year {
+                self.offer_number_year = year;
+                self.offer_number_counter = 0;
+            }
+            self.offer_number_counter += 1;
+            format!("{}-{}-{:06}", self.offer_number_prefix, year, self.offer_number_counter)
+        }
+
+        /// Internal function: Record the approval of a signer and accept the offer once the approval threshold is reached
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of the signer
+        ///
+        /// Returns '(OfferData, Option<Bucket>)':
+        /// - the updated data of the offer (still NEW if the threshold has not been reached)
+        /// - the Invoice NFT of the offer (none until the offer is accepted, or for a milestone offer, invoiced by milestone)
+        ///
+        fn accept(&mut self, offer_id: &NonFungibleLocalId, signer_badge: NonFungibleLocalId) -> (OfferData, Option<Bucket>) {
+            let now = Clock::current_time_rounded_to_seconds();
+            
+            // Update the state of the Offer
+            let nfdata = self.check_customer(offer_id, &signer_badge);
+
+            info!("Accepting an offer for this pdf {:?} with this expiry date {:?} at time  {:?} ",nfdata.hash_pdf, nfdata.expiry_date, now);
+
+            assert!(now.compare(nfdata.expiry_date, TimeComparisonOperator::Lte), "Offer is expired!");
+            self.check_signer(&nfdata, &signer_badge);
+            let actor = self.customer_actor(&signer_badge);
+
+            let mut approvals = nfdata.approvals.clone();
+            approvals.push(signer_badge.clone());
+            self.nft_manager.update_non_fungible_data(offer_id, "approvals", approvals.clone());
+
+            //emit the event
+            Runtime::emit_event(OfferApprovalEvent { offer_id: offer_id.clone(), signer_badge: signer_badge, approved: true, reason: None, note_hash: None, approvals: approvals.len() as u32, threshold: nfdata.approval_threshold, actor: actor.clone(), timestamp: now});
+
+            if (approvals.len() as u32) < nfdata.approval_threshold {
+                info!("Approval recorded, {:?} of {:?} approvals ", approvals.len(), nfdata.approval_threshold);
+                let nfdata: OfferData = self.nft_manager.get_non_fungible_data(offer_id);
+                return (nfdata, None);
+            }
+
+            let nfdata = self.transition(offer_id, OfferState::Accepted, actor.clone());
+
+            //emit the event
+            Runtime::emit_event(AcceptedOfferEvent { offer_id: offer_id.clone(), offer: nfdata.clone(), actor: actor.clone(), timestamp: now});
+
+            let invoice = if nfdata.milestones.is_empty() {
+                let due_date = now.add_seconds(self.payment_terms).unwrap();
+                Some(self.mint_invoice(offer_id, &nfdata, None, nfdata.offer_amount, due_date, actor))
+            } else {
+                None
+            };
+
+            (nfdata, invoice)
+        }
+
+        /// Internal function: Mint an Invoice NFT with the next invoice number
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `nfdata`: the data of the offer
+        /// - `milestone_index`: the invoiced milestone, if any
+        /// - `amount`: the invoiced amount, before tax
+        /// - `due_date`: the due date of the invoice
+        /// - `actor`: the badge whose action issued the invoice, if known
+        ///
+        /// Returns 'Bucket':
+        /// - the Invoice NFT
+        ///
+        fn mint_invoice(&mut self, offer_id: &NonFungibleLocalId, nfdata: &OfferData, milestone_index: Option<u32>, amount: Decimal, due_date: Instant, actor: Option<NonFungibleGlobalId>) -> Bucket {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            self.invoice_counter += 1;
+            let invoice_id = NonFungibleLocalId::integer(self.invoice_counter);
+            let tax_amount = amount
+                .checked_mul(self.tax_rate)
+                .and_then(|tax| tax.checked_round(AMOUNT_DECIMAL_PLACES, RoundingMode::ToNearestMidpointAwayFromZero))
+                .expect("Tax amount overflow!");
+            let invoice = InvoiceData {
+                invoice_number: self.invoice_counter,
+                offer_id: offer_id.clone(),
+                milestone_index: milestone_index,
+                amount: amount,
+                tax_amount: tax_amount,
+                currency: nfdata.currency,
+                due_date: due_date,
+                create_timestamp: now,
+                customer_badge: nfdata.customer_badge.clone(),
+            };
+
+            info!("Issuing invoice {:?} for an offer for this pdf {:?} with this amount {:?} ",self.invoice_counter, nfdata.hash_pdf, amount);
+
+            let invoice_bucket = self.invoice_manager.mint_non_fungible(&invoice_id, invoice.clone());
+
+            // Keep a copy reference of the invoice for the seller
+            let mut invoices = self.invoices.get(offer_id).map(|invoices| invoices.to_vec()).unwrap_or_default();
+            invoices.push(invoice_id.clone());
+            self.invoices.insert(offer_id.clone(), invoices);
+
+            //emit the event
+            Runtime::emit_event(InvoiceIssuedEvent { offer_id: offer_id.clone(), invoice_id: invoice_id, invoice: invoice, actor: actor, timestamp: now});
+
+            invoice_bucket
+        }
+
+        /// Internal function: Record a payment on the OfferData NFT
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `nfdata`: the current data of the offer
+        /// - `amount`: the amount paid
+        ///
+        /// Returns 'Decimal':
+        /// - the amount still outstanding
+        ///
+        fn record_payment(&mut self, offer_id: &NonFungibleLocalId, nfdata: &OfferData, amount: Decimal) -> Decimal {
+            let amount_paid = nfdata.amount_paid + amount;
+            let amount_outstanding = nfdata.amount_outstanding - amount;
+            assert!(amount_outstanding >= Decimal::ZERO, "Payment is higher than the outstanding amount of the offer!");
+
+            self.nft_manager.update_non_fungible_data(offer_id, "amount_paid", amount_paid);
+            self.nft_manager.update_non_fungible_data(offer_id, "amount_outstanding", amount_outstanding);
+            amount_outstanding
+        }
+
+        /// Internal function: Put a payment in the treasury vault of its currency
+        /// 
+        /// Arguments:
+        /// - `payment`: Bucket with the payment
+        ///
+        fn deposit_to_treasury(&mut self, payment: Bucket) {
+            let currency = payment.resource_address();
+            let has_vault = self.treasury.get(&currency).is_some();
+            if has_vault {
+                self.treasury.get_mut(&currency).unwrap().put(payment);
+            } else {
+                self.treasury.insert(currency, Vault::with_bucket(payment));
+            }
+        }
+
+        /// Internal function: Release the escrowed payment of an offer to the treasury
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `by_timeout`: true if the payment is released because the customer has not confirmed the delivery
+        /// - `actor`: the Customer Badge that confirmed the delivery, if known
+        ///
+        fn release_escrow_funds(&mut self, offer_id: &NonFungibleLocalId, by_timeout: bool, actor: Option<NonFungibleGlobalId>) {
+            let now = Clock::current_time_rounded_to_seconds();
+
+            let funds = self.escrow_vaults
+                .get_mut(offer_id)
+                .expect("No escrow for this offer!")
+                .take_all();
+            let amount = funds.amount();
+            self.deposit_to_treasury(funds);
+            let nfdata = self.transition(offer_id, OfferState::FullyPaid, actor.clone());
+
+            info!("Released the escrow of an offer for this pdf {:?} with this amount {:?} at time  {:?} ",nfdata.hash_pdf, amount, now);
+
+            //emit the event
+            Runtime::emit_event(EscrowReleasedEvent { offer_id: offer_id.clone(), offer: nfdata, amount: amount, by_timeout: by_timeout, actor: actor, timestamp: now});
+        }
+
+        /// Internal function: Check that a customer can act on an offer
+        /// 
+        /// The Customer Badge has to be one of the signers of the offer (one of the approvers of the organisation, 
+        /// for an organisation offer) and the auth zone has to contain a proof of it: every signer acts 
+        /// from its own account, without the OfferData NFT
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        ///
+        /// Returns 'OfferData':
+        /// - the data of the offer
+        ///
+        fn check_customer(&self, offer_id: &NonFungibleLocalId, customer_badge: &NonFungibleLocalId) -> OfferData {
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(offer_id);
+            assert!(self.eligible_signers(&nfdata).contains(customer_badge), "Customer is not a signer of the offer!");
+
+            Runtime::assert_access_rule(rule!(require(NonFungibleGlobalId::new(
+                self.customer_badge_resource_manager.address(),
+                customer_badge.clone()
+            ))));
+
+            nfdata
+        }
+
+        /// Internal function: Check that a customer can pay an offer
+        /// 
+        /// The Customer Badge has to be one of the signers of the offer, or any member of the organisation 
+        /// (a Viewer too) for an organisation offer, and the auth zone has to contain a proof of it
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the offer
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        ///
+        /// Returns 'OfferData':
+        /// - the data of the offer
+        ///
+        fn check_member(&self, offer_id: &NonFungibleLocalId, customer_badge: &NonFungibleLocalId) -> OfferData {
+            let nfdata: OfferData = self.nft_manager.get_non_fungible_data(offer_id);
+            let members = match nfdata.organisation {
+                Some(organisation) => self.organisations.get(&organisation).expect("Organisation not found!").members.clone(),
+                None => nfdata.signers.clone(),
+            };
+            assert!(members.contains(customer_badge), "Customer is not a signer of the offer nor a member of its organisation!");
+
+            Runtime::assert_access_rule(rule!(require(NonFungibleGlobalId::new(
+                self.customer_badge_resource_manager.address(),
+                customer_badge.clone()
+            ))));
+
+            nfdata
+        }
+
+        /// Internal function: Recall a non fungible from the vault holding it
+        /// 
+        /// Arguments:
+        /// - `vault`: Address of the vault holding the non fungible
+        /// - `resource`: Resource of the non fungible, the vault has to hold it
+        /// - `local_id`: NonFungibleLocalId of the non fungible
+        ///
+        /// Returns 'NonFungibleBucket':
+        /// - the recalled non fungible
+        ///
+        fn recall_non_fungible(vault: InternalAddress, resource: ResourceAddress, local_id: &NonFungibleLocalId) -> NonFungibleBucket {
+            let recalled: Bucket = scrypto_decode(&ScryptoVmV1Api::object_call_direct(
+                vault.as_node_id(),
+                NON_FUNGIBLE_VAULT_RECALL_NON_FUNGIBLES_IDENT,
+                scrypto_args!(indexset!(local_id.clone())),
+            ))
+            .unwrap();
+            let recalled = recalled.as_non_fungible();
+            assert!(recalled.resource_address() == resource, "Vault does not contain the expected resource!");
+            assert!(recalled.non_fungible_local_id() == *local_id, "Vault does not contain this NonFungible!");
+            recalled
+        }
+
+        /// Internal function: Remove a badge from a list of members
+        /// 
+        /// Arguments:
+        /// - `registry`: the list of members (managers or customers)
+        /// - `local_id`: NonFungibleLocalId of the badge
+        ///
+        fn remove_from_registry(registry: &mut AvlTree<u64, String>, local_id: &NonFungibleLocalId) {
+            let key = Self::badge_key(local_id);
+            assert!(registry.get(&key).is_some(), "Badge is not registered!");
+            registry.remove(&key);
+        }
+
+        /// Internal function: Read a page of a list of members
+        /// 
+        /// Arguments:
+        /// - `registry`: the list of members (managers or customers)
+        /// - `cursor`: the badge id to start from (none from the first one)
+        /// - `limit`: the maximum number of members returned
+        ///
+        /// Returns '(Vec<(NonFungibleLocalId, String)>, Option<u64>)':
+        /// - the badge id and the username of the members
+        /// - the cursor of the next page, if any
+        ///
+        fn list_registry(registry: &AvlTree<u64, String>, cursor: Option<u64>, limit: u32) -> (Vec<(NonFungibleLocalId, String)>, Option<u64>) {
+            let mut next_cursor = None;
+            let members = registry
+                .range(cursor.unwrap_or(0)..)
+                .take(limit as usize)
+                .map(|(key, username, next_key)| {
+                    next_cursor = next_key;
+                    (NonFungibleLocalId::integer(key), username)
+                })
+                .collect();
+            (members, next_cursor)
+        }
+
+        /// Internal function: Read a page of the offers of an index (by customer, by manager or by state)
+        /// 
+        /// Arguments:
+        /// - `index`: the index of the offers, by key and sequence
+        /// - `key`: the customer, the manager or the state the offers are listed for
+        /// - `cursor`: the sequence to start from (none from the first offer)
+        /// - `limit`: the maximum number of offers returned
+        ///
+        /// Returns '(Vec<NonFungibleLocalId>, Option<u64>)':
+        /// - the ids of the offers
+        /// - the cursor of the next page, if any
+        ///
+        fn list_index<K: ScryptoSbor + Copy + Ord + core::fmt::Debug>(index: &AvlTree<(K, u64), NonFungibleLocalId>, key: K, cursor: Option<u64>, limit: u32) -> (Vec<NonFungibleLocalId>, Option<u64>) {
+            let mut next_cursor = None;
+            let offers = index
+                .range((key, cursor.unwrap_or(0))..=(key, u64::MAX))
+                .take(limit as usize)
+                .map(|(_, offer_id, next_key)| {
+                    next_cursor = next_key.filter(|next_key| next_key.0 == key).map(|next_key| next_key.1);
+                    offer_id
+                })
+                .collect();
+            (offers, next_cursor)
+        }
+
+        /// Internal function: Get the key of a badge in a list of members
+        /// 
+        /// Arguments:
+        /// - `local_id`: NonFungibleLocalId of the badge, an integer id
+        ///
+        /// Returns 'u64':
+        /// - the integer id of the badge
+        ///
+        fn badge_key(local_id: &NonFungibleLocalId) -> u64 {
+            match local_id {
+                NonFungibleLocalId::Integer(id) => id.value(),
+                _ => panic!("Badge id is not an integer!"),
+            }
+        }
+
+        /// Internal function: Check that the caller can approve/reject an offer internally
+        /// 
+        /// The caller has to present the Manager Badge of a senior manager, or the Admin (or Owner) badge
+        /// 
+        /// Arguments:
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'Option<NonFungibleLocalId>':
+        /// - the id of the Manager Badge (none for an Admin)
+        ///
+        fn check_approver(&self, manager_proof: Option<NonFungibleProof>) -> Option<NonFungibleLocalId> {
+            match manager_proof {
+                Some(manager_proof) => {
+                    let manager_proof = manager_proof.check_with_message(self.manager_badge_resource_manager.address(), "Invalid Manager Badge proof!");
+                    let manager = manager_proof.non_fungible::<ManagerBadge>().data();
+                    assert!(manager.senior, "Only a senior manager can approve an offer!");
+                    Some(manager_proof.non_fungible_local_id())
+                }
+                None => {
+                    Runtime::assert_access_rule(self.admin_rule.clone());
+                    None
+                }
+            }
+        }
+
+        /// Internal function: Check the badge of the caller of a method restricted to the managers
+        /// 
+        /// Without a Manager Badge the caller has to present the Admin (or Owner) badge
+        /// 
+        /// Arguments:
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'Option<NonFungibleLocalId>':
+        /// - the id of the Manager Badge (none for an Admin)
+        ///
+        fn check_manager(&self, manager_proof: Option<NonFungibleProof>) -> Option<NonFungibleLocalId> {
+            match manager_proof {
+                Some(manager_proof) => {
+                    let manager_proof = manager_proof.check_with_message(self.manager_badge_resource_manager.address(), "Invalid Manager Badge proof!");
+                    Some(manager_proof.non_fungible_local_id())
+                }
+                None => {
+                    Runtime::assert_access_rule(self.admin_rule.clone());
+                    None
+                }
+            }
+        }
+
+        /// Internal function: Emit the event of an update of a Manager Badge, with its current data
+        /// 
+        /// Arguments:
+        /// - `manager_id`: NonFungibleLocalId of the Manager Badge
+        ///
+        fn emit_manager_updated(&self, manager_id: NonFungibleLocalId) {
+            let manager: ManagerBadge = self.manager_badge_resource_manager.get_non_fungible_data(&manager_id);
+            Runtime::emit_event(ManagerUpdatedEvent { 
+                badge_id: manager_id, 
+                senior: manager.senior, 
+                max_offer_amount: manager.max_offer_amount, 
+                max_discount: manager.max_discount, 
+                allowed_currencies: manager.allowed_currencies, 
+                actor: None, 
+                timestamp: Clock::current_time_rounded_to_seconds()
+            });
+        }
+
+        /// Internal function: Check that an offer is within the limits of the manager sending it
+        /// 
+        /// Without a Manager Badge the caller has to present the Admin (or Owner) badge, that has no limits
+        /// 
+        /// Arguments:
+        /// - `manager_proof`: Proof of the Manager Badge (none for an Admin)
+        /// - `lines`: Priced lines of the offer
+        /// - `currency`: Resource the amount of the offer is denominated in
+        ///
+        /// Returns 'Option<NonFungibleLocalId>':
+        /// - the id of the Manager Badge (none for an Admin)
+        ///
+        fn check_manager_limits(&self, manager_proof: Option<NonFungibleProof>, lines: &[OfferLine], currency: ResourceAddress) -> Option<NonFungibleLocalId> {
+            match manager_proof {
+                Some(manager_proof) => {
+                    let manager_proof = manager_proof.check_with_message(self.manager_badge_resource_manager.address(), "Invalid Manager Badge proof!");
+                    let manager = manager_proof.non_fungible::<ManagerBadge>().data();
+                    if let Some(max_offer_amount) = manager.max_offer_amount {
+                        assert!(OfferLine::total_of(lines) <= max_offer_amount, "Offer amount exceeds the limit of the manager!");
+                    }
+                    if let Some(max_discount) = manager.max_discount {
+                        assert!(lines.iter().all(|line| line.discount <= max_discount), "Discount exceeds the limit of the manager!");
+                    }
+                    assert!(manager.allowed_currencies.is_empty() || manager.allowed_currencies.contains(&currency), "Currency is not allowed for the manager!");
+                    Some(manager_proof.non_fungible_local_id())
+                }
+                None => {
+                    Runtime::assert_access_rule(self.admin_rule.clone());
+                    None
+                }
+            }
+        }
+
+        /// Internal function: Get the customer badges that can sign an offer
+        /// 
+        /// Arguments:
+        /// - `nfdata`: the data of the offer
+        ///
+        /// Returns 'Vec<NonFungibleLocalId>':
+        /// - the signers of the offer, or the current approvers of the organisation for an organisation offer
+        ///
+        fn eligible_signers(&self, nfdata: &OfferData) -> Vec<NonFungibleLocalId> {
+            match nfdata.organisation {
+                Some(organisation) => self.organisations.get(&organisation).expect("Organisation not found!").approvers.clone(),
+                None => nfdata.signers.clone(),
+            }
+        }
+
+        /// Internal function: Check that a signer of an offer can approve or refuse it
+        /// 
+        /// The offer has to be waiting for a decision and the signer, already checked by check_customer, 
+        /// has not to have decided yet
+        /// 
+        /// Arguments:
+        /// - `nfdata`: the data of the offer
+        /// - `signer_badge`: NonFungibleLocalId of the Customer Badge of the signer
+        ///
+        fn check_signer(&self, nfdata: &OfferData, signer_badge: &NonFungibleLocalId) {
+            assert!(nfdata.state == OfferState::New, "Offer is not waiting for a decision!");
+            assert!(!nfdata.approvals.contains(signer_badge) && !nfdata.refusals.contains(signer_badge), "Signer has already decided on this offer!");
+        }
+
+        /// Internal function: Get the global id of a Manager Badge, to be recorded as the actor of an event
+        /// 
+        /// Arguments:
+        /// - `manager_badge`: NonFungibleLocalId of the Manager Badge (none for an Admin)
+        ///
+        /// Returns 'Option<NonFungibleGlobalId>':
+        /// - the global id of the Manager Badge
+        ///
+        fn manager_actor(&self, manager_badge: Option<NonFungibleLocalId>) -> Option<NonFungibleGlobalId> {
+            manager_badge.map(|manager_badge| NonFungibleGlobalId::new(self.manager_badge_resource_manager.address(), manager_badge))
+        }
+
+        /// Internal function: Get the global id of a Customer Badge, to be recorded as the actor of an event
+        /// 
+        /// Arguments:
+        /// - `customer_badge`: NonFungibleLocalId of the Customer Badge
+        ///
+        /// Returns 'Option<NonFungibleGlobalId>':
+        /// - the global id of the Customer Badge
+        ///
+        fn customer_actor(&self, customer_badge: &NonFungibleLocalId) -> Option<NonFungibleGlobalId> {
+            Some(NonFungibleGlobalId::new(self.customer_badge_resource_manager.address(), customer_badge.clone()))
+        }
+
+        /// Internal function: Move an offer to a new state
+        /// 
+        /// This is the only place where the state of an OfferData NFT is changed:
+        /// the transition is checked against [OfferState::can_transition_to], the timestamp tied
+        /// to the new state is updated and an OfferStateChangedEvent is emitted
+        /// 
+        /// Arguments:
+        /// - `offer_id`: NonFungibleLocalId of the OfferData NFT
+        /// - `to`: the new state of the offer
+        /// - `actor`: the badge whose action moves the offer (none for an Admin or a Manager acting by role, or anyone)
+        ///
+        /// Returns 'OfferData':
+        /// - the updated data of the offer
+        ///
+        fn transition(&mut self, offer_id: &NonFungibleLocalId, to: OfferState, actor: Option<NonFungibleGlobalId>) -> OfferData {
+            let now = Clock::current_time_rounded_to_seconds();
+            let mut nfdata: OfferData = self.nft_manager.get_non_fungible_data(offer_id);
+            let from = nfdata.state;
+
+            assert!(from.can_transition_to(to), "Offer cannot move from {:?} to {:?}!", from, to);
+            self.nft_manager.update_non_fungible_data(offer_id, "state", to);
+            nfdata.state = to;
+
+            match to {
+                OfferState::Accepted => {
+                    self.nft_manager.update_non_fungible_data(offer_id, "accepted_timestamp", Some(now));
+                    nfdata.accepted_timestamp = Some(now);
+                }
+                OfferState::Refused => {
+                    self.nft_manager.update_non_fungible_data(offer_id, "refused_timestamp", Some(now));
+                    nfdata.refused_timestamp = Some(now);
+                }
+                OfferState::Cancelled => {
+                    self.nft_manager.update_non_fungible_data(offer_id, "cancelled_timestamp", Some(now));
+                    nfdata.cancelled_timestamp = Some(now);
+                }
+                OfferState::Expired => {
+                    self.nft_manager.update_non_fungible_data(offer_id, "expired_timestamp", Some(now));
+                    nfdata.expired_timestamp = Some(now);
+                }
+                OfferState::FullyPaid => {
+                    // an escrow offer is paid only once its funds have left the escrow vault
+                    if nfdata.escrow {
+                        let escrowed = self.escrow_vaults.get(offer_id).map_or(Decimal::ZERO, |vault| vault.amount());
+                        assert!(escrowed == Decimal::ZERO, "Escrowed funds have to be released first!");
+                    }
+                    self.nft_manager.update_non_fungible_data(offer_id, "paid_timestamp", Some(now));
+                    nfdata.paid_timestamp = Some(now);
+                }
+                OfferState::Delivered => {
+                    self.nft_manager.update_non_fungible_data(offer_id, "delivered_timestamp", Some(now));
+                    nfdata.delivered_timestamp = Some(now);
+                }
+                _ => {}
+            }
+
+            // Only an offer in the NEW state is open, a decided, countered, cancelled or expired offer is not
+            if from == OfferState::New {
+                self.open_offers.remove(&(nfdata.expiry_date.seconds_since_unix_epoch, offer_id.clone()));
+            }
+            if to == OfferState::New {
+                self.open_offers.insert((nfdata.expiry_date.seconds_since_unix_epoch, offer_id.clone()), offer_id.clone());
+            }
+            let sequence = *self.offer_sequence.get(offer_id).expect("Offer is not indexed!");
+            self.offers_by_state.remove(&(from, sequence));
+            self.offers_by_state.insert((to, sequence), offer_id.clone());
+
+            Runtime::emit_event(OfferStateChangedEvent { offer_id: offer_id.clone(), from, to, actor, timestamp: now });
+            nfdata
         }
     
     }
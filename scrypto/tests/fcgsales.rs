@@ -1,5 +1,6 @@
 
 use scrypto_test::prelude::*;
+use fcgsales::fcg_sales::{CustomerOrganisation, InvoiceData, OfferData, OfferState, OfferVersion, RefusalReason};
 
 
 
@@ -13,7 +14,7 @@ fn test_fcg_sales() {
     // Create another account for a manager
     let (_manager_public_key1, _manager_private_key1, _manager_account1) = ledger.new_allocated_account();
     // Create another account for a customer
-    let (_customer_public_key1, _customer_private_key1, customer_account1) = ledger.new_allocated_account();    
+    let (customer_public_key1, _customer_private_key1, customer_account1) = ledger.new_allocated_account();    
     let (_customer_public_key2, _customer_private_key2, _customer_account2) = ledger.new_allocated_account();
     
     // Create an owner and an admin badge
@@ -47,22 +48,21 @@ fn test_fcg_sales() {
     let _owner_badge = receipt.expect_commit(true).new_resource_addresses()[0];
     let admin_badge = receipt.expect_commit(true).new_resource_addresses()[1];
     let nft_manager = receipt.expect_commit(true).new_resource_addresses()[4];
-    let manager_badge_resource_manager = receipt.expect_commit(true).new_resource_addresses()[3];
-    let customer_badge_resource_manager = receipt.expect_commit(true).new_resource_addresses()[4];    
+    let manager_badge_resource_manager = receipt.expect_commit(true).new_resource_addresses()[2];
+    let customer_badge_resource_manager = receipt.expect_commit(true).new_resource_addresses()[3];    
+    let invoice_manager = receipt.expect_commit(true).new_resource_addresses()[5];
 
 
     println!(" NFT Manager = {:?}\n", nft_manager);
     println!(" Manager = {:?}\n", manager_badge_resource_manager);
     println!(" Customer = {:?}\n", customer_badge_resource_manager);
 
-    //Send an offer
-    let offer_amount = dec!(400);
-    let expiry_date = dec!(3000);
+    //Accept XRD as a currency for the offers
     let receipt = ledger.execute_manifest(
-        send_offer_as_admin(component, account, admin_badge, customer_account1, "hash_pdf".to_string(), expiry_date, offer_amount),
+        add_currency(component, account, admin_badge, XRD),
         vec![NonFungibleGlobalId::from_public_key(&public_key)],
     );
-    println!("Receipt from send_offer {:?}\n", receipt);
+    println!("Receipt from add_currency {:?}\n", receipt);
     receipt.expect_commit_success();
 
     //Send a badge to a Customer Account to let it accept an offer
@@ -72,30 +72,1130 @@ fn test_fcg_sales() {
     );
     println!("Receipt from mint_customer_badge {:?}\n", receipt);
     receipt.expect_commit_success();
+    let customer_badge_id = NonFungibleLocalId::integer(1);
 
-    //TODO 
-    // fetch the GlobalId of the Manager's Badge to execute 'send_offer_as_manager' function
+    //Send an offer bound to the Customer badge
+    let offer_amount = dec!(400);
+    // 2100-01-01T00:00:00Z
+    let expiry_date = Instant::new(4102444800);
+    let receipt = ledger.execute_manifest(
+        send_offer_as_admin(component, account, admin_badge, customer_account1, customer_badge_id.clone(), "hash_pdf".to_string(), expiry_date, offer_amount),
+        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+    );
+    println!("Receipt from send_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
 
     //TODO 
-    // same for the customer, fetch the GlobalId of the Customer's Badge to execute 'accept_offer' function
+    // fetch the GlobalId of the Manager's Badge to execute 'send_offer_as_manager' function
 
     //Accept an offer
-    let offerdata_nft_global_id = NonFungibleGlobalId::new(nft_manager, NonFungibleLocalId::integer(1));
-    let customer_badge_global_id = NonFungibleGlobalId::new(customer_badge_resource_manager, NonFungibleLocalId::integer(4));
+    let offer_id = offer_ids(&mut ledger, customer_account1, nft_manager)[0].clone();
+    let customer_badge_global_id = NonFungibleGlobalId::new(customer_badge_resource_manager, customer_badge_id);
     let receipt = ledger.execute_manifest(
-        accept_offer(component, customer_account1, customer_badge_global_id, offerdata_nft_global_id),
-        vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        accept_offer(component, customer_account1, customer_badge_global_id, offer_id),
+        vec![NonFungibleGlobalId::from_public_key(&customer_public_key1)],
     );
     println!("Receipt from accept_offer {:?}\n", receipt);
     receipt.expect_commit_success();
 
+    //The Customer has received the first Invoice
+    let invoice_ids = offer_ids(&mut ledger, customer_account1, invoice_manager);
+    assert_eq!(invoice_ids, vec![NonFungibleLocalId::integer(1)]);
+
+
+}
+
+#[test]
+fn test_cancel_offer() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+
+    //Cancel a NEW offer, its OfferData NFT is recalled from the customer account
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let offer_vault = env.vault_of(customer.account, env.nft_manager);
+    let receipt = env.call_as_admin("cancel_offer", (offer_id.clone(), Some(offer_vault), None::<ManifestProof>), env.admin_account);
+    println!("Receipt from cancel_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::Cancelled);
+    assert!(offer.cancelled_timestamp.is_some());
+    assert!(!offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&offer_id));
+
+    //An ACCEPTED offer cannot be cancelled
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone())).expect_commit_success();
+    let receipt = env.call_as_admin("cancel_offer", (offer_id.clone(), Some(offer_vault), None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "Offer cannot move from Accepted to Cancelled!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Accepted);
+
+    //A REFUSED offer cannot be cancelled
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    env.call_as_customer(&customer, "refuse_offer", (offer_id.clone(), customer.badge_id.clone(), manifest_enum(0), None::<String>)).expect_commit_success();
+    let receipt = env.call_as_admin("cancel_offer", (offer_id.clone(), Some(offer_vault), None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "Offer cannot move from Refused to Cancelled!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Refused);
+
+    //A customer cannot cancel an offer
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let receipt = env.call_as_customer(&customer, "cancel_offer", (offer_id.clone(), Some(offer_vault), None::<ManifestProof>));
+    receipt.expect_commit_failure();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+
+    //The OfferData NFT of an offer waiting for an internal approval is taken from the component, no vault is needed
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(800)));
+    assert_eq!(env.offer(&offer_id).state, OfferState::PendingApproval);
+    let held = env.held_offers();
+    env.call_as_admin("cancel_offer", (offer_id.clone(), None::<InternalAddress>, None::<ManifestProof>), env.admin_account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Cancelled);
+    assert_eq!(env.held_offers(), held);
+
+    //The vault is needed for an offer held by the customer
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let receipt = env.call_as_admin("cancel_offer", (offer_id.clone(), None::<InternalAddress>, None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "The vault holding the offer is needed to cancel it!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+
+    //With burn_on_cancel the recalled OfferData NFT is burned instead of being kept by the component
+    env.call_as_admin("set_burn_on_cancel", (true,), env.admin_account).expect_commit_success();
+    let held = env.held_offers();
+    env.call_as_admin("cancel_offer", (offer_id.clone(), Some(offer_vault), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    assert!(!offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&offer_id));
+    assert_eq!(env.held_offers(), held);
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(800)));
+    let held = env.held_offers();
+    env.call_as_admin("cancel_offer", (offer_id.clone(), None::<InternalAddress>, None::<ManifestProof>), env.admin_account).expect_commit_success();
+    assert_eq!(env.held_offers(), held - dec!(1));
+}
+
+#[test]
+fn test_offer_lines() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+
+    //The amount of the offer is the sum of its lines, each one rounded to AMOUNT_DECIMAL_PLACES
+    let lines = vec![
+        ("SKU1".to_string(), "description_hash_1".to_string(), dec!(2), dec!("100.125"), dec!("0.1")),
+        ("SKU2".to_string(), "description_hash_2".to_string(), dec!(3), dec!("33.333"), dec!(0)),
+        ("SKU3".to_string(), "description_hash_3".to_string(), dec!(1), dec!(50), dec!(1)),
+    ];
+    let offer_id = env.send_offer(&customer, Terms { lines, ..Terms::new(dec!(400)) });
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.lines.len(), 3);
+    assert_eq!(offer.lines[0].total(), dec!("180.23"));
+    assert_eq!(offer.lines[1].total(), dec!(100));
+    assert_eq!(offer.lines[2].total(), dec!(0));
+    assert_eq!(offer.offer_amount, dec!("280.23"));
+    assert_eq!(offer.amount_outstanding, dec!("280.23"));
+
+    //The discount is a ratio between 0 and 1
+    let lines = vec![("SKU1".to_string(), "description_hash".to_string(), dec!(1), dec!(400), dec!("1.5"))];
+    let receipt = env.try_send_offer(&customer, Terms { lines, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Discount must be between 0 and 1!");
+    let lines = vec![("SKU1".to_string(), "description_hash".to_string(), dec!(1), dec!(400), dec!("-0.1"))];
+    let receipt = env.try_send_offer(&customer, Terms { lines, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Discount must be between 0 and 1!");
+
+    //An amount that does not fit in a Decimal is refused
+    let lines = vec![("SKU1".to_string(), "description_hash".to_string(), Decimal::MAX, dec!(2), dec!(0))];
+    let receipt = env.try_send_offer(&customer, Terms { lines, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Offer line amount overflow!");
+    let lines = vec![line(dec!("2000000000000000000000000000000000000000")), line(dec!("2000000000000000000000000000000000000000"))];
+    let receipt = env.try_send_offer(&customer, Terms { lines, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Offer amount overflow!");
+}
+
+#[test]
+fn test_offer_numbers() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+
+    //The offers are numbered PREFIX-YYYY-NNNNNN in sequence
+    let first = env.send_offer(&customer, Terms::new(dec!(400)));
+    let second = env.send_offer(&customer, Terms::new(dec!(400)));
+    assert_eq!(env.offer(&first).offer_number, "OFF-2026-000001");
+    assert_eq!(env.offer(&second).offer_number, "OFF-2026-000002");
+
+    //An offer can be found by its number
+    let found: Option<NonFungibleLocalId> = env.call_public("get_offer_by_number", ("OFF-2026-000002".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, Some(second));
+    let found: Option<NonFungibleLocalId> = env.call_public("get_offer_by_number", ("OFF-2026-000003".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, None);
+
+    //The sequence restarts every year
+    env.set_time(START_TIME + 364 * DAY);
+    let last_of_year = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + 394 * DAY), ..Terms::new(dec!(400)) });
+    assert_eq!(env.offer(&last_of_year).offer_number, "OFF-2026-000003");
+    env.set_time(START_TIME + 365 * DAY);
+    let first_of_year = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + 395 * DAY), ..Terms::new(dec!(400)) });
+    assert_eq!(env.offer(&first_of_year).offer_number, "OFF-2027-000001");
+
+    //The admin can change the prefix, the sequence goes on
+    let receipt = env.call_as_admin("set_offer_number_prefix", (String::new(),), env.admin_account);
+    expect_failure(receipt, "Prefix cannot be empty!");
+    let receipt = env.call_as_customer(&customer, "set_offer_number_prefix", ("FCG".to_string(),));
+    receipt.expect_commit_failure();
+    env.call_as_admin("set_offer_number_prefix", ("FCG".to_string(),), env.admin_account).expect_commit_success();
+    let offer_id = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + 395 * DAY), ..Terms::new(dec!(400)) });
+    assert_eq!(env.offer(&offer_id).offer_number, "FCG-2027-000002");
+    let found: Option<NonFungibleLocalId> = env.call_public("get_offer_by_number", ("FCG-2027-000002".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, Some(offer_id));
+}
+
+#[test]
+fn test_currencies() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let token = env.ledger.create_fungible_resource(dec!(1000), 18, env.admin_account);
+    let currencies: Vec<ResourceAddress> = env.call_public("get_currencies", ()).expect_commit_success().output(1);
+    assert_eq!(currencies, vec![XRD]);
+
+    //An offer cannot be sent in a currency that is not accepted
+    let receipt = env.try_send_offer(&customer, Terms { currency: token, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Currency is not accepted!");
+
+    //Only a fungible resource can be accepted, and only once
+    let receipt = env.call_as_admin("add_currency", (env.customer_badge,), env.admin_account);
+    expect_failure(receipt, "A currency has to be a fungible resource!");
+    env.call_as_admin("add_currency", (token,), env.admin_account).expect_commit_success();
+    let receipt = env.call_as_admin("add_currency", (token,), env.admin_account);
+    expect_failure(receipt, "Currency is already accepted!");
+    let currencies: Vec<ResourceAddress> = env.call_public("get_currencies", ()).expect_commit_success().output(1);
+    assert_eq!(currencies, vec![XRD, token]);
+    let offer_id = env.send_offer(&customer, Terms { currency: token, ..Terms::new(dec!(400)) });
+    assert_eq!(env.offer(&offer_id).currency, token);
+
+    //Once the currency is removed no new offer can be sent in it, the offers already sent are not changed
+    let receipt = env.call_as_admin("remove_currency", (token,), env.admin_account);
+    println!("Receipt from remove_currency {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let currencies: Vec<ResourceAddress> = env.call_public("get_currencies", ()).expect_commit_success().output(1);
+    assert_eq!(currencies, vec![XRD]);
+    let receipt = env.try_send_offer(&customer, Terms { currency: token, ..Terms::new(dec!(400)) });
+    expect_failure(receipt, "Currency is not accepted!");
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.currency, token);
+    assert_eq!(offer.state, OfferState::New);
+    let receipt = env.call_as_admin("remove_currency", (token,), env.admin_account);
+    expect_failure(receipt, "Currency is not accepted!");
+
+    //Only the admin can change the currencies
+    let receipt = env.call_as_customer(&customer, "remove_currency", (XRD,));
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn test_expire_offers() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let expiring = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + DAY), ..Terms::new(dec!(400)) });
+    let open = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + 10 * DAY), ..Terms::new(dec!(400)) });
+
+    //Nothing expires before the expiry date
+    let expired: Vec<NonFungibleLocalId> = env.call_public("expire_offers", (10u32,)).expect_commit_success().output(1);
+    assert!(expired.is_empty());
+
+    //Anyone can move the offers past their expiry date to the EXPIRED state
+    env.set_time(START_TIME + 2 * DAY);
+    let receipt = env.call_public("expire_offers", (10u32,));
+    println!("Receipt from expire_offers {:?}\n", receipt);
+    let expired: Vec<NonFungibleLocalId> = receipt.expect_commit_success().output(1);
+    assert_eq!(expired, vec![expiring.clone()]);
+    let offer = env.offer(&expiring);
+    assert_eq!(offer.state, OfferState::Expired);
+    assert!(offer.expired_timestamp.is_some());
+    assert_eq!(env.offer(&open).state, OfferState::New);
+
+    //An expired offer is not expired twice and cannot be accepted anymore
+    let expired: Vec<NonFungibleLocalId> = env.call_public("expire_offers", (10u32,)).expect_commit_success().output(1);
+    assert!(expired.is_empty());
+    let receipt = env.call_as_customer(&customer, "accept_offer", (expiring.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "Offer is expired!");
+
+    //The sweep stops at the limit, the remaining offers are expired by the next call
+    env.set_time(START_TIME + 11 * DAY);
+    let later = env.send_offer(&customer, Terms { expiry_date: Instant::new(START_TIME + 12 * DAY), ..Terms::new(dec!(400)) });
+    env.set_time(START_TIME + 13 * DAY);
+    let expired: Vec<NonFungibleLocalId> = env.call_public("expire_offers", (1u32,)).expect_commit_success().output(1);
+    assert_eq!(expired, vec![open.clone()]);
+    let expired: Vec<NonFungibleLocalId> = env.call_public("expire_offers", (1u32,)).expect_commit_success().output(1);
+    assert_eq!(expired, vec![later.clone()]);
+}
+
+#[test]
+fn test_counter_offer() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let expiry_date = Instant::new(START_TIME + 30 * DAY);
+
+    //A counter-proposal rejected by the manager puts the offer back in the NEW state
+    let receipt = env.call_as_customer(&customer, "counter_offer", (offer_id.clone(), customer.badge_id.clone(), dec!(350), "hash_pdf_2".to_string(), "note_hash".to_string()));
+    println!("Receipt from counter_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Countered);
+    env.call_as_admin("reject_counter", (offer_id.clone(), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+    let receipt = env.call_as_admin("accept_counter", (offer_id.clone(), expiry_date, None::<ManifestProof>), customer.account);
+    expect_failure(receipt, "No pending counter-proposal for this offer!");
+
+    //A counter-proposal accepted by the manager supersedes the offer with a new one, sent to the customer
+    env.call_as_customer(&customer, "counter_offer", (offer_id.clone(), customer.badge_id.clone(), dec!(350), "hash_pdf_2".to_string(), "note_hash".to_string())).expect_commit_success();
+    let receipt = env.call_as_admin("accept_counter", (offer_id.clone(), expiry_date, None::<ManifestProof>), customer.account);
+    println!("Receipt from accept_counter {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Superseded);
+    let new_offer_id = env.last_offer(&customer.badge_id);
+    let new_offer = env.offer(&new_offer_id);
+    assert_eq!(new_offer.state, OfferState::New);
+    assert_eq!(new_offer.offer_amount, dec!(350));
+    assert_eq!(new_offer.hash_pdf, "hash_pdf_2");
+    assert_eq!(new_offer.parent_offer, Some(offer_id.clone()));
+    assert!(offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&new_offer_id));
+
+    //The superseded offer is closed, the new one can be accepted
+    let receipt = env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "Offer is not waiting for a decision!");
+    let receipt = env.call_as_customer(&customer, "counter_offer", (offer_id.clone(), customer.badge_id.clone(), dec!(300), "hash_pdf_3".to_string(), "note_hash".to_string()));
+    expect_failure(receipt, "Offer cannot move from Superseded to Countered!");
+    env.call_as_customer(&customer, "accept_offer", (new_offer_id.clone(), customer.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&new_offer_id).state, OfferState::Accepted);
+}
+
+#[test]
+fn test_amend_offer() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+
+    //Amend the expiry date, then the lines of the offer
+    let expiry_date = Instant::new(START_TIME + 60 * DAY);
+    let receipt = env.call_as_admin("amend_offer", (offer_id.clone(), (Some(expiry_date), None::<Vec<Line>>), None::<ManifestProof>), env.admin_account);
+    println!("Receipt from amend_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let lines = vec![("SKU1".to_string(), "description_hash".to_string(), dec!(2), dec!(150), dec!("0.1"))];
+    env.call_as_admin("amend_offer", (offer_id.clone(), (None::<Instant>, Some(lines)), None::<ManifestProof>), env.admin_account).expect_commit_success();
+
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.version, 3);
+    assert_eq!(offer.expiry_date, expiry_date);
+    assert_eq!(offer.offer_amount, dec!(270));
+    assert_eq!(offer.amount_outstanding, dec!(270));
+
+    //The previous versions are kept on ledger
+    let history: Vec<OfferVersion> = env.call_public("get_offer_history", (offer_id.clone(),)).expect_commit_success().output(1);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].version, 1);
+    assert_eq!(history[0].expiry_date, Instant::new(START_TIME + 30 * DAY));
+    assert_eq!(history[0].offer_amount, dec!(400));
+    assert_eq!(history[1].version, 2);
+    assert_eq!(history[1].expiry_date, expiry_date);
+    assert_eq!(history[1].offer_amount, dec!(400));
+
+    //An amendment has to change something
+    let receipt = env.call_as_admin("amend_offer", (offer_id.clone(), (None::<Instant>, None::<Vec<Line>>), None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "No changes requested!");
+
+    //An accepted offer cannot be amended anymore
+    env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone())).expect_commit_success();
+    let receipt = env.call_as_admin("amend_offer", (offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(500))])), None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "Only a NEW offer can be amended!");
+    assert_eq!(env.offer(&offer_id).version, 3);
+}
+
+#[test]
+fn test_accept_and_pay() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let balance = env.balance(customer.account);
+
+    //The customer pays more than the amount of the offer, the change is returned
+    let receipt = env.pay(&customer, "accept_and_pay", &offer_id, dec!(500));
+    println!("Receipt from accept_and_pay {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.balance(customer.account), balance - dec!(400));
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::FullyPaid);
+    assert_eq!(offer.amount_paid, dec!(400));
+    assert_eq!(offer.amount_outstanding, dec!(0));
+    assert!(offer.paid_timestamp.is_some());
+    assert_eq!(offer_ids(&mut env.ledger, customer.account, env.invoice_manager).len(), 1);
+
+    //A paid offer cannot be paid again
+    let receipt = env.pay(&customer, "accept_and_pay", &offer_id, dec!(400));
+    expect_failure(receipt, "Offer is not waiting for a decision!");
+    assert_eq!(env.balance(customer.account), balance - dec!(400));
+
+    //The payment is credited to the treasury
+    let admin_balance = env.balance(env.admin_account);
+    env.call_as_admin("withdraw_treasury", (XRD, dec!(400)), env.admin_account).expect_commit_success();
+    assert_eq!(env.balance(env.admin_account), admin_balance + dec!(400));
+}
+
+#[test]
+fn test_invoices() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+
+    //The tax rate cannot be negative
+    let receipt = env.call_as_admin("set_tax_rate", (dec!("-0.1"),), env.admin_account);
+    expect_failure(receipt, "Tax rate cannot be negative!");
+    env.call_as_admin("set_tax_rate", (dec!("0.22"),), env.admin_account).expect_commit_success();
+    env.call_as_admin("set_payment_terms", (10 * DAY,), env.admin_account).expect_commit_success();
+
+    //The invoice issued on acceptance carries the tax and is due after the payment terms
+    let offer_id = env.send_offer(&customer, Terms { lines: vec![line(dec!("400.25"))], ..Terms::new(dec!(400)) });
+    env.set_time(START_TIME + DAY);
+    let receipt = env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone()));
+    println!("Receipt from accept_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let invoice_ids = offer_ids(&mut env.ledger, customer.account, env.invoice_manager);
+    assert_eq!(invoice_ids, vec![NonFungibleLocalId::integer(1)]);
+    let invoice = env.ledger.get_non_fungible_data::<InvoiceData>(env.invoice_manager, invoice_ids[0].clone());
+    assert_eq!(invoice.invoice_number, 1);
+    assert_eq!(invoice.offer_id, offer_id);
+    assert_eq!(invoice.milestone_index, None);
+    assert_eq!(invoice.amount, dec!("400.25"));
+    assert_eq!(invoice.tax_amount, dec!("88.06"));
+    assert_eq!(invoice.currency, XRD);
+    assert_eq!(invoice.customer_badge, customer.badge_id);
+    assert_eq!(invoice.create_timestamp, Instant::new(START_TIME + DAY));
+    assert_eq!(invoice.due_date, Instant::new(START_TIME + DAY + 10 * DAY));
+
+    //The seller keeps a reference of the invoices of the offer
+    let invoices: Vec<NonFungibleLocalId> = env.call_public("get_invoices", (offer_id.clone(),)).expect_commit_success().output(1);
+    assert_eq!(invoices, invoice_ids);
+}
+
+#[test]
+fn test_withdraw_treasury() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+
+    //Nothing can be withdrawn before a payment is received
+    let receipt = env.call_as_admin("withdraw_treasury", (XRD, dec!(1)), env.admin_account);
+    expect_failure(receipt, "No payments received in this currency!");
+
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    env.pay(&customer, "accept_and_pay", &offer_id, dec!(400)).expect_commit_success();
+
+    //A customer cannot withdraw from the treasury
+    let receipt = env.call_as_customer(&customer, "withdraw_treasury", (XRD, dec!(400)));
+    receipt.expect_commit_failure();
+
+    //The admin cannot withdraw more than the payments received
+    let receipt = env.call_as_admin("withdraw_treasury", (XRD, dec!(401)), env.admin_account);
+    receipt.expect_commit_failure();
+
+    //The admin withdraws the payments in two steps
+    let admin_balance = env.balance(env.admin_account);
+    let receipt = env.call_as_admin("withdraw_treasury", (XRD, dec!(150)), env.admin_account);
+    println!("Receipt from withdraw_treasury {:?}\n", receipt);
+    receipt.expect_commit_success();
+    env.call_as_admin("withdraw_treasury", (XRD, dec!(250)), env.admin_account).expect_commit_success();
+    assert_eq!(env.balance(env.admin_account), admin_balance + dec!(400));
+    let receipt = env.call_as_admin("withdraw_treasury", (XRD, dec!(1)), env.admin_account);
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn test_escrow_confirmed_delivery() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms { escrow: true, deposit_ratio: dec!(1), ..Terms::new(dec!(400)) });
+
+    //An escrow offer has to be paid on acceptance
+    let receipt = env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "An escrow offer has to be paid on acceptance, use accept_and_pay!");
+
+    //The payment is held in escrow, not in the treasury
+    let receipt = env.pay(&customer, "accept_and_pay", &offer_id, dec!(400));
+    println!("Receipt from accept_and_pay {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Escrowed);
+    let receipt = env.call_as_admin("withdraw_treasury", (XRD, dec!(400)), env.admin_account);
+    expect_failure(receipt, "No payments received in this currency!");
+
+    //The customer cannot confirm a delivery that has not been marked yet
+    let receipt = env.call_as_customer(&customer, "confirm_delivery", (offer_id.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "Offer cannot move from Escrowed to FullyPaid!");
+
+    //The manager marks the offer as delivered, the customer confirms it and the escrow is released to the treasury
+    let receipt = env.call_as_admin("mark_delivered", (offer_id.clone(), None::<ManifestProof>), env.admin_account);
+    println!("Receipt from mark_delivered {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::Delivered);
+    assert!(offer.delivered_timestamp.is_some());
+
+    //The balance of an escrow offer cannot be paid, the escrow is only released by confirming the delivery
+    let receipt = env.pay(&customer, "pay_balance", &offer_id, dec!(0));
+    expect_failure(receipt, "An escrow offer is paid on acceptance, use confirm_delivery to release it!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Delivered);
+
+    let receipt = env.call_as_customer(&customer, "confirm_delivery", (offer_id.clone(), customer.badge_id.clone()));
+    println!("Receipt from confirm_delivery {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::FullyPaid);
+
+    let admin_balance = env.balance(env.admin_account);
+    env.call_as_admin("withdraw_treasury", (XRD, dec!(400)), env.admin_account).expect_commit_success();
+    assert_eq!(env.balance(env.admin_account), admin_balance + dec!(400));
+    assert_eq!(env.ledger.get_component_balance(env.component, XRD), dec!(0));
+
+    //The escrow is released only once
+    let receipt = env.call_as_customer(&customer, "confirm_delivery", (offer_id.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "Offer cannot move from FullyPaid to FullyPaid!");
+}
+
+#[test]
+fn test_escrow_released_by_timeout() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms { escrow: true, deposit_ratio: dec!(1), ..Terms::new(dec!(400)) });
+    env.pay(&customer, "accept_and_pay", &offer_id, dec!(400)).expect_commit_success();
+
+    //The escrow cannot be released before the delivery
+    let receipt = env.call_public("release_escrow", (offer_id.clone(),));
+    expect_failure(receipt, "Offer has not been delivered!");
+
+    //Nor before the escrow timeout (14 days) after the delivery
+    env.call_as_admin("mark_delivered", (offer_id.clone(), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    env.set_time(START_TIME + 13 * DAY);
+    let receipt = env.call_public("release_escrow", (offer_id.clone(),));
+    expect_failure(receipt, "Escrow timeout has not passed yet!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Delivered);
+
+    //After the timeout anyone can release the escrow to the treasury, without the customer
+    env.set_time(START_TIME + 14 * DAY);
+    let receipt = env.call_public("release_escrow", (offer_id.clone(),));
+    println!("Receipt from release_escrow {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::FullyPaid);
+
+    let admin_balance = env.balance(env.admin_account);
+    env.call_as_admin("withdraw_treasury", (XRD, dec!(400)), env.admin_account).expect_commit_success();
+    assert_eq!(env.balance(env.admin_account), admin_balance + dec!(400));
+    assert_eq!(env.ledger.get_component_balance(env.component, XRD), dec!(0));
+}
+
+#[test]
+fn test_deposit_and_pay_balance() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let offer_id = env.send_offer(&customer, Terms { deposit_ratio: dec!("0.3"), ..Terms::new(dec!(400)) });
+    let balance = env.balance(customer.account);
+
+    //The deposit has to be paid on acceptance
+    let receipt = env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone()));
+    expect_failure(receipt, "A deposit has to be paid on acceptance, use accept_and_pay!");
+    let receipt = env.pay(&customer, "accept_and_pay", &offer_id, dec!(100));
+    expect_failure(receipt, "Payment is lower than the deposit of the offer!");
+
+    //Paying the deposit leaves the offer partially paid
+    let receipt = env.pay(&customer, "accept_and_pay", &offer_id, dec!(120));
+    println!("Receipt from accept_and_pay {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::PartiallyPaid);
+    assert_eq!(offer.amount_paid, dec!(120));
+    assert_eq!(offer.amount_outstanding, dec!(280));
+
+    //The balance has to be paid in full
+    let receipt = env.pay(&customer, "pay_balance", &offer_id, dec!(200));
+    expect_failure(receipt, "Payment is lower than the outstanding amount of the offer!");
+
+    //Paying more than the balance returns the change
+    let receipt = env.pay(&customer, "pay_balance", &offer_id, dec!(300));
+    println!("Receipt from pay_balance {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::FullyPaid);
+    assert_eq!(offer.amount_paid, dec!(400));
+    assert_eq!(offer.amount_outstanding, dec!(0));
+    assert_eq!(env.balance(customer.account), balance - dec!(400));
+
+    //A paid offer has no balance left, and an offer not accepted yet has no balance to pay
+    let receipt = env.pay(&customer, "pay_balance", &offer_id, dec!(1));
+    expect_failure(receipt, "Offer is not in progress!");
+    let new_offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let receipt = env.pay(&customer, "pay_balance", &new_offer_id, dec!(400));
+    expect_failure(receipt, "Offer is not in progress!");
+    assert_eq!(env.offer(&new_offer_id).state, OfferState::New);
+
+    let admin_balance = env.balance(env.admin_account);
+    env.call_as_admin("withdraw_treasury", (XRD, dec!(400)), env.admin_account).expect_commit_success();
+    assert_eq!(env.balance(env.admin_account), admin_balance + dec!(400));
+}
+
+#[test]
+fn test_pay_milestones() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let milestones = vec![
+        ("milestone1_hash".to_string(), dec!(100), Instant::new(START_TIME + 30 * DAY)),
+        ("milestone2_hash".to_string(), dec!(300), Instant::new(START_TIME + 60 * DAY)),
+    ];
+    let offer_id = env.send_offer(&customer, Terms { milestones, ..Terms::new(dec!(400)) });
+
+    //A milestone offer is accepted without an invoice, the invoices are issued by milestone
+    env.call_as_customer(&customer, "accept_offer", (offer_id.clone(), customer.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Accepted);
+    let invoices: Vec<NonFungibleLocalId> = env.call_public("get_invoices", (offer_id.clone(),)).expect_commit_success().output(1);
+    assert!(invoices.is_empty());
+
+    //A milestone cannot be paid before it is delivered
+    let receipt = env.pay_milestone(&customer, &offer_id, 0, dec!(100));
+    expect_failure(receipt, "Milestone has not been delivered yet!");
+
+    //The manager marks the first milestone as delivered, the customer claims its invoice
+    let receipt = env.call_as_admin("mark_milestone_delivered", (offer_id.clone(), 0u32, None::<ManifestProof>), env.admin_account);
+    println!("Receipt from mark_milestone_delivered {:?}\n", receipt);
+    receipt.expect_commit_success();
+    env.call_as_customer(&customer, "claim_invoices", (offer_id.clone(), customer.badge_id.clone())).expect_commit_success();
+    assert_eq!(offer_ids(&mut env.ledger, customer.account, env.invoice_manager), vec![NonFungibleLocalId::integer(1)]);
+    let receipt = env.call_as_admin("mark_milestone_delivered", (offer_id.clone(), 0u32, None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "Milestone has already been delivered!");
+
+    //The first milestone is paid for its amount, and only once
+    let receipt = env.pay_milestone(&customer, &offer_id, 0, dec!(90));
+    expect_failure(receipt, "Payment is lower than the amount of the milestone!");
+    let receipt = env.pay_milestone(&customer, &offer_id, 0, dec!(100));
+    println!("Receipt from pay_milestone {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::PartiallyPaid);
+    assert_eq!(offer.amount_paid, dec!(100));
+    assert!(offer.milestones[0].paid_timestamp.is_some());
+    let receipt = env.pay_milestone(&customer, &offer_id, 0, dec!(100));
+    expect_failure(receipt, "Milestone has already been paid!");
+
+    //Paying the last milestone pays the offer in full
+    env.call_as_admin("mark_milestone_delivered", (offer_id.clone(), 1u32, None::<ManifestProof>), env.admin_account).expect_commit_success();
+    env.pay_milestone(&customer, &offer_id, 1, dec!(300)).expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::FullyPaid);
+    assert_eq!(offer.amount_outstanding, dec!(0));
+
+    //A milestone offer is not paid with pay_balance
+    let receipt = env.pay(&customer, "pay_balance", &offer_id, dec!(1));
+    expect_failure(receipt, "A milestone offer is paid by milestone, use pay_milestone!");
+}
+
+#[test]
+fn test_quorum_acceptance() {
+    let mut env = setup();
+    let procurement = env.new_customer("procurement");
+    let cfo = env.new_customer("cfo");
+    let legal = env.new_customer("legal");
+    let outsider = env.new_customer("outsider");
+    let quorum = Some((vec![cfo.badge_id.clone(), legal.badge_id.clone()], 2u32, false));
+    let offer_id = env.send_offer(&procurement, Terms { quorum: quorum.clone(), ..Terms::new(dec!(400)) });
+
+    //A co-signer approves from its own account, without holding the OfferData NFT
+    let receipt = env.call_as_customer(&cfo, "accept_offer", (offer_id.clone(), cfo.badge_id.clone()));
+    println!("Receipt from accept_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::New);
+    assert_eq!(offer.approvals, vec![cfo.badge_id.clone()]);
+    let receipt = env.call_as_customer(&cfo, "accept_offer", (offer_id.clone(), cfo.badge_id.clone()));
+    expect_failure(receipt, "Signer has already decided on this offer!");
+
+    //Only the signers can decide, each with its own badge
+    let receipt = env.call_as_customer(&outsider, "accept_offer", (offer_id.clone(), outsider.badge_id.clone()));
+    expect_failure(receipt, "Customer is not a signer of the offer!");
+    let receipt = env.call_as_customer(&outsider, "accept_offer", (offer_id.clone(), legal.badge_id.clone()));
+    receipt.expect_commit_failure();
+
+    //A refusal keeps the offer open while the threshold can still be reached, its reason and note are in the event
+    let receipt = env.call_as_customer(&legal, "refuse_offer", (offer_id.clone(), legal.badge_id.clone(), manifest_enum(2), Some("legal_note_hash".to_string())));
+    let approval_events: Vec<OfferApprovalEvent> = events(&mut env.ledger, &receipt, "OfferApprovalEvent");
+    assert_eq!(approval_events, vec![OfferApprovalEvent {
+        offer_id: offer_id.clone(),
+        signer_badge: legal.badge_id.clone(),
+        approved: false,
+        reason: Some(RefusalReason::Competitor),
+        note_hash: Some("legal_note_hash".to_string()),
+        approvals: 1,
+        threshold: 2,
+        actor: Some(NonFungibleGlobalId::new(env.customer_badge, legal.badge_id.clone())),
+        timestamp: Instant::new(START_TIME),
+    }]);
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::New);
+    assert_eq!(offer.refusals, vec![legal.badge_id.clone()]);
+    assert_eq!(offer.refusal_reason, None);
+
+    //The second approval reaches the threshold, the invoice goes to the signer who completed the acceptance
+    env.call_as_customer(&procurement, "accept_offer", (offer_id.clone(), procurement.badge_id.clone())).expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::Accepted);
+    assert_eq!(offer.approvals, vec![cfo.badge_id.clone(), procurement.badge_id.clone()]);
+    assert_eq!(offer_ids(&mut env.ledger, procurement.account, env.invoice_manager).len(), 1);
+
+    //The offer is refused once the threshold cannot be reached anymore
+    let offer_id = env.send_offer(&procurement, Terms { quorum, ..Terms::new(dec!(400)) });
+    env.call_as_customer(&cfo, "refuse_offer", (offer_id.clone(), cfo.badge_id.clone(), manifest_enum(0), None::<String>)).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+    let receipt = env.call_as_customer(&legal, "refuse_offer", (offer_id.clone(), legal.badge_id.clone(), manifest_enum(0), Some("note_hash".to_string())));
+    println!("Receipt from refuse_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::Refused);
+    assert_eq!(offer.refusal_reason, Some(RefusalReason::Price));
+    assert_eq!(offer.refusal_note_hash, Some("note_hash".to_string()));
+    let receipt = env.call_as_customer(&procurement, "accept_offer", (offer_id.clone(), procurement.badge_id.clone()));
+    expect_failure(receipt, "Offer is not waiting for a decision!");
+
+    //Only the refused offers are counted, by the reason of the refusal that closed them
+    let counts: Vec<(RefusalReason, u64)> = env.call_public("get_refusal_counts", ()).expect_commit_success().output(1);
+    assert_eq!(counts, vec![
+        (RefusalReason::Price, 1),
+        (RefusalReason::Timing, 0),
+        (RefusalReason::Competitor, 0),
+        (RefusalReason::Scope, 0),
+        (RefusalReason::Other, 0),
+    ]);
+}
+
+#[test]
+fn test_amend_above_approval_limit() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+
+    //An offer above the approval limit is held by the component until it is approved
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(800)));
+    assert_eq!(env.offer(&offer_id).state, OfferState::PendingApproval);
+    assert!(!offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&offer_id));
+    env.call_as_admin("approve_offer", (offer_id.clone(), None::<ManifestProof>), customer.account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+    assert!(offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&offer_id));
+
+    //An amendment cannot raise an offer above the approval limit
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+    let receipt = env.call_as_admin("amend_offer", (offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(600))])), None::<ManifestProof>), env.admin_account);
+    expect_failure(receipt, "Amended amount is above the approval limit, send a new offer!");
+    assert_eq!(env.offer(&offer_id).offer_amount, dec!(400));
+
+    //An amendment within the approval limit is applied
+    env.call_as_admin("amend_offer", (offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(450))])), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).offer_amount, dec!(450));
+}
+
+#[test]
+fn test_accept_counter_above_approval_limit() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+    let offer_id = env.send_offer(&customer, Terms::new(dec!(400)));
+
+    //A counter-proposal above the approval limit is accepted as a new offer waiting for the approval
+    env.call_as_customer(&customer, "counter_offer", (offer_id.clone(), customer.badge_id.clone(), dec!(700), "hash_pdf_2".to_string(), "note_hash".to_string())).expect_commit_success();
+    let receipt = env.call_as_admin("accept_counter", (offer_id.clone(), Instant::new(START_TIME + 30 * DAY), None::<ManifestProof>), customer.account);
+    println!("Receipt from accept_counter {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Superseded);
+    let new_offer_id = env.last_offer(&customer.badge_id);
+    let new_offer = env.offer(&new_offer_id);
+    assert_eq!(new_offer.state, OfferState::PendingApproval);
+    assert_eq!(new_offer.offer_amount, dec!(700));
+    assert_eq!(new_offer.parent_offer, Some(offer_id.clone()));
+    assert!(!offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&new_offer_id));
+
+    //Once approved the new offer is sent to the customer
+    env.call_as_admin("approve_offer", (new_offer_id.clone(), None::<ManifestProof>), customer.account).expect_commit_success();
+    assert_eq!(env.offer(&new_offer_id).state, OfferState::New);
+    assert!(offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&new_offer_id));
+}
+
+#[test]
+fn test_internal_approval() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let sales = env.new_manager("sales");
+    let director = env.new_manager("director");
+    let junior = env.new_manager("junior");
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+    let expiry_date = Instant::new(START_TIME + 30 * DAY);
+    let global_id = NonFungibleGlobalId::new(env.manager_badge, sales.badge_id.clone());
+    env.ledger.execute_manifest(
+        send_offer_as_manager(env.component, sales.account, global_id.clone(), customer.account, customer.badge_id.clone(), "hash_pdf".to_string(), expiry_date, dec!(800)),
+        vec![NonFungibleGlobalId::from_public_key(&sales.key)],
+    ).expect_commit_success();
+    let offer_id = env.last_offer(&customer.badge_id);
+    assert_eq!(env.offer(&offer_id).state, OfferState::PendingApproval);
+
+    //Only a senior manager can approve an offer
+    let receipt = env.call_as_manager(&junior, "approve_offer", |proof| (offer_id.clone(), Some(proof)), customer.account);
+    expect_failure(receipt, "Only a senior manager can approve an offer!");
+
+    //A senior manager cannot approve its own offer
+    env.call_as_admin("set_senior_manager", (sales.badge_id.clone(), true), env.admin_account).expect_commit_success();
+    let receipt = env.call_as_manager(&sales, "approve_offer", |proof| (offer_id.clone(), Some(proof)), customer.account);
+    expect_failure(receipt, "A manager cannot approve its own offer!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::PendingApproval);
+
+    //Another senior manager approves it, the OfferData NFT is sent to the customer
+    env.call_as_admin("set_senior_manager", (director.badge_id.clone(), true), env.admin_account).expect_commit_success();
+    let receipt = env.call_as_manager(&director, "approve_offer", |proof| (offer_id.clone(), Some(proof)), customer.account);
+    println!("Receipt from approve_offer {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+    assert!(offer_ids(&mut env.ledger, customer.account, env.nft_manager).contains(&offer_id));
+
+    //A rejected offer goes back to the manager as a DRAFT, which can only be cancelled
+    env.ledger.execute_manifest(
+        send_offer_as_manager(env.component, sales.account, global_id, customer.account, customer.badge_id.clone(), "hash_pdf".to_string(), expiry_date, dec!(900)),
+        vec![NonFungibleGlobalId::from_public_key(&sales.key)],
+    ).expect_commit_success();
+    let offer_id = env.last_offer(&customer.badge_id);
+    let receipt = env.call_as_manager(&director, "reject_internal", |proof| (offer_id.clone(), "reason_hash".to_string(), Some(proof)), sales.account);
+    println!("Receipt from reject_internal {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer = env.offer(&offer_id);
+    assert_eq!(offer.state, OfferState::Draft);
+    assert_eq!(offer.internal_rejection_hash, Some("reason_hash".to_string()));
+    assert!(offer_ids(&mut env.ledger, sales.account, env.nft_manager).contains(&offer_id));
+    let offer_vault = env.vault_of(sales.account, env.nft_manager);
+    env.call_as_manager(&sales, "cancel_offer", |proof| (offer_id.clone(), Some(offer_vault), Some(proof)), sales.account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Cancelled);
+    assert!(!offer_ids(&mut env.ledger, sales.account, env.nft_manager).contains(&offer_id));
+}
+
+#[test]
+fn test_manager_limits() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let manager = env.new_manager("manager1");
+
+    //The limits are validated: the amount cannot be negative and the discount is a ratio between 0 and 1
+    let receipt = env.call_as_admin("set_manager_limits", (manager.badge_id.clone(), Some(dec!(-1)), None::<Decimal>, Vec::<ResourceAddress>::new()), env.admin_account);
+    expect_failure(receipt, "Maximum offer amount cannot be negative!");
+    let receipt = env.call_as_admin("set_manager_limits", (manager.badge_id.clone(), None::<Decimal>, Some(dec!("1.5")), Vec::<ResourceAddress>::new()), env.admin_account);
+    expect_failure(receipt, "Maximum discount must be between 0 and 1!");
+    let receipt = env.call_as_admin("set_manager_limits", (manager.badge_id.clone(), None::<Decimal>, Some(dec!("-0.1")), Vec::<ResourceAddress>::new()), env.admin_account);
+    expect_failure(receipt, "Maximum discount must be between 0 and 1!");
+    env.call_as_admin("set_manager_limits", (manager.badge_id.clone(), Some(dec!(500)), None::<Decimal>, Vec::<ResourceAddress>::new()), env.admin_account).expect_commit_success();
+
+    //The manager sends an offer within its limits, the offer records the badge of the manager
+    let global_id = NonFungibleGlobalId::new(env.manager_badge, manager.badge_id.clone());
+    let receipt = env.ledger.execute_manifest(
+        send_offer_as_manager(env.component, manager.account, global_id.clone(), customer.account, customer.badge_id.clone(), "hash_pdf".to_string(), Instant::new(START_TIME + 30 * DAY), dec!(400)),
+        vec![NonFungibleGlobalId::from_public_key(&manager.key)],
+    );
+    println!("Receipt from send_offer_as_manager {:?}\n", receipt);
+    receipt.expect_commit_success();
+    let offer_id = env.last_offer(&customer.badge_id);
+    assert_eq!(env.offer(&offer_id).issued_by, Some(manager.badge_id.clone()));
+    let receipt = env.ledger.execute_manifest(
+        send_offer_as_manager(env.component, manager.account, global_id, customer.account, customer.badge_id.clone(), "hash_pdf".to_string(), Instant::new(START_TIME + 30 * DAY), dec!(600)),
+        vec![NonFungibleGlobalId::from_public_key(&manager.key)],
+    );
+    expect_failure(receipt, "Offer amount exceeds the limit of the manager!");
+
+    //The manager cannot amend the offer above its limit
+    let receipt = env.call_as_manager(&manager, "amend_offer", |proof| (offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(600))])), Some(proof)), env.admin_account);
+    expect_failure(receipt, "Offer amount exceeds the limit of the manager!");
+    env.call_as_manager(&manager, "amend_offer", |proof| (offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(450))])), Some(proof)), env.admin_account).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).offer_amount, dec!(450));
+
+    //The manager cannot accept a counter-proposal above its limit, the admin has no limits
+    env.call_as_customer(&customer, "counter_offer", (offer_id.clone(), customer.badge_id.clone(), dec!(700), "hash_pdf_2".to_string(), "note_hash".to_string())).expect_commit_success();
+    let expiry_date = Instant::new(START_TIME + 30 * DAY);
+    let receipt = env.call_as_manager(&manager, "accept_counter", |proof| (offer_id.clone(), expiry_date, Some(proof)), customer.account);
+    expect_failure(receipt, "Offer amount exceeds the limit of the manager!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Countered);
+    env.call_as_admin("accept_counter", (offer_id.clone(), expiry_date, None::<ManifestProof>), customer.account).expect_commit_success();
+    let new_offer_id = env.last_offer(&customer.badge_id);
+    assert_eq!(env.offer(&new_offer_id).offer_amount, dec!(700));
+    assert_eq!(env.offer(&new_offer_id).issued_by, None);
+
+    //A manager without a badge proof is treated as an admin, and refused
+    let receipt = env.call_as_manager(&manager, "amend_offer", |_proof| (new_offer_id.clone(), (None::<Instant>, Some(vec![line(dec!(650))])), None::<ManifestProof>), env.admin_account);
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn test_revoke_customer() {
+    let mut env = setup();
+    let _customer1 = env.new_customer("azienda1");
+    let customer2 = env.new_customer("azienda2");
+    assert_eq!(customer2.badge_id, NonFungibleLocalId::integer(2));
+
+    //The customer has a NEW, an ACCEPTED, a COUNTERED and another NEW offer
+    let new_offer = env.send_offer(&customer2, Terms::new(dec!(400)));
+    let accepted_offer = env.send_offer(&customer2, Terms::new(dec!(400)));
+    env.call_as_customer(&customer2, "accept_offer", (accepted_offer.clone(), customer2.badge_id.clone())).expect_commit_success();
+    let countered_offer = env.send_offer(&customer2, Terms::new(dec!(400)));
+    env.call_as_customer(&customer2, "counter_offer", (countered_offer.clone(), customer2.badge_id.clone(), dec!(350), "hash_pdf_2".to_string(), "note_hash".to_string())).expect_commit_success();
+    let last_offer = env.send_offer(&customer2, Terms::new(dec!(400)));
+
+    //Revoking the badge cancels the open offers of the first page only
+    let badge_vault = env.vault_of(customer2.account, env.customer_badge);
+    let receipt = env.call_as_admin("revoke_customer", (customer2.badge_id.clone(), badge_vault, 2u32, None::<ManifestProof>), env.admin_account);
+    println!("Receipt from revoke_customer {:?}\n", receipt);
+    let cursor: Option<u64> = receipt.expect_commit_success().output(2);
+    assert!(cursor.is_some());
+    assert_eq!(env.offer(&new_offer).state, OfferState::Cancelled);
+    assert_eq!(env.offer(&accepted_offer).state, OfferState::Accepted);
+    assert_eq!(env.offer(&countered_offer).state, OfferState::Countered);
+    assert!(offer_ids(&mut env.ledger, customer2.account, env.customer_badge).is_empty());
+    let found: Option<NonFungibleLocalId> = env.call_public("find_customer", ("azienda2".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, None);
+
+    //The next pages are cancelled with cancel_customer_offers, COUNTERED offers included
+    let receipt = env.call_as_admin("cancel_customer_offers", (customer2.badge_id.clone(), cursor, 10u32, None::<ManifestProof>), env.admin_account);
+    let (cancelled, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = receipt.expect_commit_success().output(2);
+    assert_eq!(cancelled, vec![countered_offer.clone(), last_offer.clone()]);
+    assert_eq!(cursor, None);
+    assert_eq!(env.offer(&countered_offer).state, OfferState::Cancelled);
+    assert_eq!(env.offer(&last_offer).state, OfferState::Cancelled);
+
+    //The id of a revoked badge is never reused, its username can be registered again
+    let customer3 = env.new_customer("azienda3");
+    assert_eq!(customer3.badge_id, NonFungibleLocalId::integer(3));
+    let customer4 = env.new_customer("azienda2");
+    assert_eq!(customer4.badge_id, NonFungibleLocalId::integer(4));
+}
+
+#[test]
+fn test_revoke_customer_with_burned_offers() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    env.call_as_admin("set_burn_on_cancel", (true,), env.admin_account).expect_commit_success();
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+
+    //The customer has a cancelled offer whose OfferData NFT has been burned, a NEW offer and an offer pending approval
+    let burned_offer = env.send_offer(&customer, Terms::new(dec!(400)));
+    let offer_vault = env.vault_of(customer.account, env.nft_manager);
+    env.call_as_admin("cancel_offer", (burned_offer.clone(), Some(offer_vault), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    let new_offer = env.send_offer(&customer, Terms::new(dec!(400)));
+    let pending_offer = env.send_offer(&customer, Terms::new(dec!(800)));
+    let held = env.held_offers();
+
+    //The burned offer is skipped, the open ones are cancelled and the pending one is burned too
+    let badge_vault = env.vault_of(customer.account, env.customer_badge);
+    let receipt = env.call_as_admin("revoke_customer", (customer.badge_id.clone(), badge_vault, 10u32, None::<ManifestProof>), env.admin_account);
+    println!("Receipt from revoke_customer {:?}\n", receipt);
+    let cursor: Option<u64> = receipt.expect_commit_success().output(2);
+    assert_eq!(cursor, None);
+    assert_eq!(env.offer(&new_offer).state, OfferState::Cancelled);
+    assert_eq!(env.held_offers(), held - dec!(1));
+
+    //Going through the offers again finds nothing left to cancel
+    let receipt = env.call_as_admin("cancel_customer_offers", (customer.badge_id.clone(), None::<u64>, 10u32, None::<ManifestProof>), env.admin_account);
+    let (cancelled, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = receipt.expect_commit_success().output(2);
+    assert!(cancelled.is_empty());
+    assert_eq!(cursor, None);
+    let (offers, _): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_state", (manifest_enum(5), None::<u64>, 10u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![burned_offer, new_offer, pending_offer]);
+}
+
+#[test]
+fn test_revoke_manager() {
+    let mut env = setup();
+    let manager1 = env.new_manager("manager1");
+    let manager2 = env.new_manager("manager2");
+
+    //The badge is recalled and burned, the manager is removed from the registry
+    let badge_vault = env.vault_of(manager1.account, env.manager_badge);
+    let receipt = env.call_as_admin("revoke_manager", (manager1.badge_id.clone(), badge_vault), env.admin_account);
+    println!("Receipt from revoke_manager {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert!(offer_ids(&mut env.ledger, manager1.account, env.manager_badge).is_empty());
+    let (managers, cursor): (Vec<(NonFungibleLocalId, String)>, Option<u64>) = env.call_public("list_managers", (None::<u64>, 10u32)).expect_commit_success().output(1);
+    assert_eq!(managers, vec![(manager2.badge_id.clone(), "manager2".to_string())]);
+    assert_eq!(cursor, None);
+    let found: Option<NonFungibleLocalId> = env.call_public("find_manager", ("manager1".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, None);
+    let found: Option<NonFungibleLocalId> = env.call_public("find_manager", ("manager2".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, Some(manager2.badge_id.clone()));
+
+    //A badge that has been revoked cannot be revoked again
+    let receipt = env.call_as_admin("revoke_manager", (manager1.badge_id.clone(), badge_vault), env.admin_account);
+    receipt.expect_commit_failure();
+
+    //Only the admin can revoke a manager
+    let badge_vault = env.vault_of(manager2.account, env.manager_badge);
+    let receipt = env.call_as_manager(&manager2, "revoke_manager", |_proof| (manager2.badge_id.clone(), badge_vault), manager2.account);
+    receipt.expect_commit_failure();
+    assert_eq!(offer_ids(&mut env.ledger, manager2.account, env.manager_badge), vec![manager2.badge_id.clone()]);
+}
+
+#[test]
+fn test_unique_usernames() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let manager = env.new_manager("manager1");
+
+    //A username is taken only once by a customer and once by a manager
+    let receipt = env.ledger.execute_manifest(
+        mint_customer_badge(env.component, env.admin_account, env.admin_badge, customer.account, "azienda1".to_string()),
+        vec![NonFungibleGlobalId::from_public_key(&env.admin_key)],
+    );
+    expect_failure(receipt, "Username is already taken by a customer!");
+    let receipt = env.call_as_admin("mint_manager_badge", ("manager1".to_string(),), manager.account);
+    expect_failure(receipt, "Username is already taken by a manager!");
+    let other = env.new_customer("manager1");
 
+    //The members are found by username and listed by badge id, a page at a time
+    let found: Option<NonFungibleLocalId> = env.call_public("find_customer", ("azienda1".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, Some(customer.badge_id.clone()));
+    let found: Option<NonFungibleLocalId> = env.call_public("find_manager", ("manager1".to_string(),)).expect_commit_success().output(1);
+    assert_eq!(found, Some(manager.badge_id.clone()));
+    let (members, cursor): (Vec<(NonFungibleLocalId, String)>, Option<u64>) = env.call_public("list_customers", (None::<u64>, 1u32)).expect_commit_success().output(1);
+    assert_eq!(members, vec![(customer.badge_id.clone(), "azienda1".to_string())]);
+    assert!(cursor.is_some());
+    let (members, cursor): (Vec<(NonFungibleLocalId, String)>, Option<u64>) = env.call_public("list_customers", (cursor, 1u32)).expect_commit_success().output(1);
+    assert_eq!(members, vec![(other.badge_id.clone(), "manager1".to_string())]);
+    assert_eq!(cursor, None);
+}
+
+#[test]
+fn test_organisation_members() {
+    let mut env = setup();
+    let receipt = env.call_as_admin("create_organisation", ("legal_name_hash".to_string(), "vat_number_hash".to_string(), None::<ManifestProof>), env.admin_account);
+    println!("Receipt from create_organisation {:?}\n", receipt);
+    let organisation: u64 = receipt.expect_commit_success().output(2);
+    // CustomerRole::Approver and CustomerRole::Viewer
+    let buyer = env.new_member("buyer", organisation, manifest_enum(1));
+    let cfo = env.new_member("cfo", organisation, manifest_enum(1));
+    let viewer = env.new_member("viewer", organisation, manifest_enum(0));
+    let org: Option<CustomerOrganisation> = env.call_public("get_organisation", (organisation,)).expect_commit_success().output(1);
+    let org = org.expect("Organisation not found!");
+    assert_eq!(org.members, vec![buyer.badge_id.clone(), cfo.badge_id.clone(), viewer.badge_id.clone()]);
+    assert_eq!(org.approvers, vec![buyer.badge_id.clone(), cfo.badge_id.clone()]);
+
+    //The approvers of the organisation sign the offer, each from its own account
+    let offer_id = env.send_offer(&buyer, Terms { quorum: Some((vec![], 2u32, true)), ..Terms::new(dec!(400)) });
+    let receipt = env.call_as_customer(&viewer, "accept_offer", (offer_id.clone(), viewer.badge_id.clone()));
+    expect_failure(receipt, "Customer is not a signer of the offer!");
+    env.call_as_customer(&cfo, "accept_offer", (offer_id.clone(), cfo.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::New);
+    env.call_as_customer(&buyer, "accept_offer", (offer_id.clone(), buyer.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Accepted);
+
+    //Any member of the organisation can pay, a Viewer included
+    let offer_id = env.send_offer(&buyer, Terms { deposit_ratio: dec!("0.5"), ..Terms::new(dec!(200)) });
+    env.pay(&cfo, "accept_and_pay", &offer_id, dec!(100)).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::PartiallyPaid);
+    let receipt = env.pay(&viewer, "pay_balance", &offer_id, dec!(100));
+    println!("Receipt from pay_balance {:?}\n", receipt);
+    receipt.expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::FullyPaid);
+
+    //A Viewer cannot release the escrow of an offer, an approver confirms its delivery
+    let offer_id = env.send_offer(&buyer, Terms { escrow: true, deposit_ratio: dec!(1), ..Terms::new(dec!(200)) });
+    env.pay(&buyer, "accept_and_pay", &offer_id, dec!(200)).expect_commit_success();
+    env.call_as_admin("mark_delivered", (offer_id.clone(), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    let receipt = env.call_as_customer(&viewer, "confirm_delivery", (offer_id.clone(), viewer.badge_id.clone()));
+    expect_failure(receipt, "Customer is not a signer of the offer!");
+    assert_eq!(env.offer(&offer_id).state, OfferState::Delivered);
+    env.call_as_customer(&cfo, "confirm_delivery", (offer_id.clone(), cfo.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::FullyPaid);
+
+    //A Viewer promoted to Approver can sign
+    env.call_as_admin("set_customer_role", (viewer.badge_id.clone(), manifest_enum(1), None::<ManifestProof>), env.admin_account).expect_commit_success();
+    let offer_id = env.send_offer(&buyer, Terms::new(dec!(400)));
+    env.call_as_customer(&viewer, "accept_offer", (offer_id.clone(), viewer.badge_id.clone())).expect_commit_success();
+    assert_eq!(env.offer(&offer_id).state, OfferState::Accepted);
+}
+
+#[test]
+fn test_offer_indexes() {
+    let mut env = setup();
+    let customer1 = env.new_customer("azienda1");
+    let customer2 = env.new_customer("azienda2");
+    let offer1 = env.send_offer(&customer1, Terms::new(dec!(400)));
+    let offer2 = env.send_offer(&customer2, Terms::new(dec!(400)));
+    let offer3 = env.send_offer(&customer1, Terms::new(dec!(400)));
+    let offer4 = env.send_offer(&customer1, Terms::new(dec!(400)));
+
+    //The offers of a customer are listed in the order they were sent, a page at a time
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_customer", (customer1.badge_id.clone(), None::<u64>, 2u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer1.clone(), offer3.clone()]);
+    assert!(cursor.is_some());
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_customer", (customer1.badge_id.clone(), cursor, 2u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer4.clone()]);
+    assert_eq!(cursor, None);
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_customer", (customer2.badge_id.clone(), None::<u64>, 2u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer2.clone()]);
+    assert_eq!(cursor, None);
+
+    //The index by state follows the transitions of the offers
+    env.call_as_customer(&customer1, "accept_offer", (offer3.clone(), customer1.badge_id.clone())).expect_commit_success();
+    // OfferState::New and OfferState::Accepted
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_state", (manifest_enum(2), None::<u64>, 10u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer1.clone(), offer2.clone(), offer4.clone()]);
+    assert_eq!(cursor, None);
+    let (offers, _): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_state", (manifest_enum(3), None::<u64>, 10u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer3.clone()]);
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_state", (manifest_enum(2), None::<u64>, 2u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer1.clone(), offer2.clone()]);
+    let (offers, cursor): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_state", (manifest_enum(2), cursor, 2u32)).expect_commit_success().output(1);
+    assert_eq!(offers, vec![offer4.clone()]);
+    assert_eq!(cursor, None);
+
+    //The offers sent by the admin are not indexed by manager
+    let manager = env.new_manager("manager1");
+    let (offers, _): (Vec<NonFungibleLocalId>, Option<u64>) = env.call_public("offers_by_manager", (manager.badge_id.clone(), None::<u64>, 10u32)).expect_commit_success().output(1);
+    assert!(offers.is_empty());
+}
+
+#[test]
+fn test_offer_events() {
+    let mut env = setup();
+    let customer = env.new_customer("azienda1");
+    let manager = env.new_manager("manager1");
+    let global_id = NonFungibleGlobalId::new(env.manager_badge, manager.badge_id.clone());
+    let manager_actor = Some(global_id.clone());
+    env.call_as_admin("set_approval_limit", (XRD, Some(dec!(500))), env.admin_account).expect_commit_success();
+
+    //An offer waiting for an internal approval is created, but not sent yet
+    let receipt = env.ledger.execute_manifest(
+        send_offer_as_manager(env.component, manager.account, global_id, customer.account, customer.badge_id.clone(), "hash_pdf".to_string(), Instant::new(START_TIME + 30 * DAY), dec!(800)),
+        vec![NonFungibleGlobalId::from_public_key(&manager.key)],
+    );
+    let offer_id = env.last_offer(&customer.badge_id);
+    let created: Vec<CreatedOfferEvent> = events(&mut env.ledger, &receipt, "CreatedOfferEvent");
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].offer_id, offer_id);
+    assert_eq!(created[0].offer.state, OfferState::PendingApproval);
+    assert_eq!(created[0].actor, manager_actor);
+    assert_eq!(created[0].timestamp, Instant::new(START_TIME));
+    let sent: Vec<SentOfferEvent> = events(&mut env.ledger, &receipt, "SentOfferEvent");
+    assert!(sent.is_empty());
+
+    //The offer is sent once approved
+    env.set_time(START_TIME + DAY);
+    let receipt = env.call_as_admin("approve_offer", (offer_id.clone(), None::<ManifestProof>), customer.account);
+    let sent: Vec<SentOfferEvent> = events(&mut env.ledger, &receipt, "SentOfferEvent");
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].offer_id, offer_id);
+    assert_eq!(sent[0].offer.state, OfferState::New);
+    assert_eq!(sent[0].actor, None);
+    assert_eq!(sent[0].timestamp, Instant::new(START_TIME + DAY));
+
+    //The manager cancels it, the event records the badge of the manager
+    env.set_time(START_TIME + 2 * DAY);
+    let offer_vault = env.vault_of(customer.account, env.nft_manager);
+    let receipt = env.call_as_manager(&manager, "cancel_offer", |proof| (offer_id.clone(), Some(offer_vault), Some(proof)), env.admin_account);
+    let cancelled: Vec<CancelledOfferEvent> = events(&mut env.ledger, &receipt, "CancelledOfferEvent");
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].offer_id, offer_id);
+    assert_eq!(cancelled[0].offer.state, OfferState::Cancelled);
+    assert!(!cancelled[0].burned);
+    assert_eq!(cancelled[0].actor, manager_actor);
+    assert_eq!(cancelled[0].timestamp, Instant::new(START_TIME + 2 * DAY));
 }
 
 /// Send an offer by using an Admin badge
 /// 
 /// 
-fn send_offer_as_admin(component: ComponentAddress, admin_account: ComponentAddress, admin_badge: ResourceAddress, customer_account: ComponentAddress, hash_pdf: String, expiry_date: Decimal, offer_amount: Decimal) -> TransactionManifestV1 {
+fn send_offer_as_admin(component: ComponentAddress, admin_account: ComponentAddress, admin_badge: ResourceAddress, customer_account: ComponentAddress, customer_badge_id: NonFungibleLocalId, hash_pdf: String, expiry_date: Instant, offer_amount: Decimal) -> TransactionManifestV1 {
     ManifestBuilder::new()
     .lock_fee_from_faucet() 
     .create_proof_from_account_of_amount(admin_account, admin_badge, dec!(1))
@@ -105,8 +1205,14 @@ fn send_offer_as_admin(component: ComponentAddress, admin_account: ComponentAddr
         |_lookup| (  
             hash_pdf, // #1
             expiry_date, // #2
-            offer_amount,
-            customer_account
+            vec![("SKU1".to_string(), "description_hash".to_string(), dec!(1), offer_amount, dec!(0))],
+            XRD,
+            false,
+            dec!(0),
+            Vec::<(String, Decimal, Instant)>::new(),
+            customer_badge_id,
+            None::<(Vec<NonFungibleLocalId>, u32, bool)>,
+            None::<ManifestProof>
         )
     )
     .try_deposit_entire_worktop_or_abort(customer_account,  None)    
@@ -116,18 +1222,26 @@ fn send_offer_as_admin(component: ComponentAddress, admin_account: ComponentAddr
 /// Send an offer by using a Manager NonFungible badge
 /// 
 /// 
-fn _send_offer_as_manager(component: ComponentAddress, manager_account: ComponentAddress, global_id: NonFungibleGlobalId, customer_account: ComponentAddress, hash_pdf: String, expiry_date: Decimal, offer_amount: Decimal) -> TransactionManifestV1 {
+fn send_offer_as_manager(component: ComponentAddress, manager_account: ComponentAddress, global_id: NonFungibleGlobalId, customer_account: ComponentAddress, customer_badge_id: NonFungibleLocalId, hash_pdf: String, expiry_date: Instant, offer_amount: Decimal) -> TransactionManifestV1 {
     ManifestBuilder::new()
     .lock_fee_from_faucet() 
-    .create_proof_from_account_of_non_fungible(manager_account, global_id)
+    .create_proof_from_account_of_non_fungible(manager_account, global_id.clone())
+    // a copy of the badge proof is passed to the component, the badge stays in the auth zone for the manager role
+    .create_proof_from_auth_zone_of_non_fungibles(global_id.resource_address(), [global_id.local_id().clone()], "manager_badge")
     .call_method_with_name_lookup(  // #1
         component,
         "send_offer",
-        |_lookup| (  
+        |lookup| (  
             hash_pdf, // #1
             expiry_date, // #2
-            offer_amount,
-            customer_account // #4
+            vec![("SKU1".to_string(), "description_hash".to_string(), dec!(1), offer_amount, dec!(0))],
+            XRD,
+            false,
+            dec!(0),
+            Vec::<(String, Decimal, Instant)>::new(),
+            customer_badge_id, // #8
+            None::<(Vec<NonFungibleLocalId>, u32, bool)>,
+            Some(lookup.proof("manager_badge"))
         )
     )
     .try_deposit_entire_worktop_or_abort(customer_account, None)    
@@ -137,17 +1251,35 @@ fn _send_offer_as_manager(component: ComponentAddress, manager_account: Componen
 /// Accept an offer by using a Customer NonFungible badge
 /// 
 /// 
-fn accept_offer(component: ComponentAddress, customer_account: ComponentAddress, customer_badge_global_id: NonFungibleGlobalId, offerdata_nft_global_id: NonFungibleGlobalId) -> TransactionManifestV1 {
+fn accept_offer(component: ComponentAddress, customer_account: ComponentAddress, customer_badge_global_id: NonFungibleGlobalId, offer_id: NonFungibleLocalId) -> TransactionManifestV1 {
+    let signer_badge = customer_badge_global_id.local_id().clone();
     ManifestBuilder::new()
     .lock_fee_from_faucet() 
     .create_proof_from_account_of_non_fungible(customer_account, customer_badge_global_id)
-    .create_proof_from_account_of_non_fungible(customer_account, offerdata_nft_global_id)
-    .pop_from_auth_zone("offer_data")
     .call_method_with_name_lookup(  // #1
         component,
         "accept_offer",
-        |lookup| (  
-            lookup.proof("offer_data"),
+        |_lookup| (  
+            offer_id,
+            signer_badge,
+        )
+    )
+    .try_deposit_entire_worktop_or_abort(customer_account, None)
+    .build()
+}    
+
+/// Add a currency an offer can be denominated in
+/// 
+/// 
+fn add_currency(component: ComponentAddress, admin_account: ComponentAddress, admin_badge: ResourceAddress, currency: ResourceAddress) -> TransactionManifestV1 {
+    ManifestBuilder::new()
+    .lock_fee_from_faucet() 
+    .create_proof_from_account_of_amount(admin_account, admin_badge, dec!(1))
+    .call_method_with_name_lookup(  // #1
+        component,
+        "add_currency",
+        |_lookup| (  
+            currency,
         )
     )
     .build()
@@ -165,11 +1297,380 @@ fn mint_customer_badge(component: ComponentAddress, admin_account: ComponentAddr
         "mint_customer_badge",
         |_lookup| (  
             username,
+            None::<u64>,
+            // CustomerRole::Approver
+            ManifestValue::Enum { discriminator: 1, fields: vec![] },
+            None::<ManifestProof>,
         )
     )
     .try_deposit_entire_worktop_or_abort(customer_account,  None)    
     .build()
-}    
+}
 
+/// Get the NFT ids of a resource (OfferData, Invoice) held by an account
+/// 
+/// 
+fn offer_ids(ledger: &mut DefaultLedgerSimulator, account: ComponentAddress, nft_manager: ResourceAddress) -> Vec<NonFungibleLocalId> {
+    // the account has no vault until it receives the resource
+    let vaults = ledger.get_component_vaults(account, nft_manager);
+    match vaults.first() {
+        Some(vault_id) => {
+            let (_amount, ids) = ledger.inspect_non_fungible_vault(*vault_id).unwrap();
+            ids.collect()
+        }
+        None => vec![],
+    }
+}
+
+/// Time of the tests: 2026-01-01T00:00:00Z
+const START_TIME: i64 = 1767225600;
+/// Seconds in a day
+const DAY: i64 = 24 * 3600;
+
+/// A priced line of an offer: sku, description hash, quantity, unit price and discount
+type Line = (String, String, Decimal, Decimal, Decimal);
+
+/// A single line of the given amount
+/// 
+/// 
+fn line(amount: Decimal) -> Line {
+    ("SKU1".to_string(), "description_hash".to_string(), dec!(1), amount, dec!(0))
+}
+
+/// A fieldless enum value (e.g. RefusalReason, CustomerRole, OfferState) by its position in the enum
+/// 
+/// 
+fn manifest_enum(discriminator: u8) -> ManifestValue {
+    ManifestValue::Enum { discriminator, fields: vec![] }
+}
+
+/// Check that a transaction failed with the given panic message
+/// 
+/// 
+fn expect_failure(receipt: TransactionReceiptV1, message: &str) {
+    receipt.expect_specific_failure(|error| format!("{:?}", error).contains(message));
+}
+
+/// Decode the events with the given name emitted in a successful transaction
+/// 
+/// 
+fn events<T: ScryptoDecode>(ledger: &mut DefaultLedgerSimulator, receipt: &TransactionReceiptV1, name: &str) -> Vec<T> {
+    receipt
+        .expect_commit_success()
+        .application_events
+        .iter()
+        .filter(|(event_type, _)| ledger.event_name(event_type) == name)
+        .map(|(_, data)| scrypto_decode::<T>(data).unwrap())
+        .collect()
+}
+
+/// The OfferApprovalEvent emitted by the component, with the same fields in the same order
+/// 
+/// 
+#[derive(ScryptoSbor, PartialEq, Debug)]
+struct OfferApprovalEvent {
+    offer_id: NonFungibleLocalId,
+    signer_badge: NonFungibleLocalId,
+    approved: bool,
+    reason: Option<RefusalReason>,
+    note_hash: Option<String>,
+    approvals: u32,
+    threshold: u32,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+/// The CreatedOfferEvent emitted by the component, with the same fields in the same order
+/// 
+/// 
+#[derive(ScryptoSbor)]
+struct CreatedOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+/// The SentOfferEvent emitted by the component, with the same fields in the same order
+/// 
+/// 
+#[derive(ScryptoSbor)]
+struct SentOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+/// The CancelledOfferEvent emitted by the component, with the same fields in the same order
+/// 
+/// 
+#[derive(ScryptoSbor)]
+struct CancelledOfferEvent {
+    offer_id: NonFungibleLocalId,
+    offer: OfferData,
+    burned: bool,
+    actor: Option<NonFungibleGlobalId>,
+    timestamp: Instant,
+}
+
+/// Terms of an offer sent by the Admin, with a single line of the amount of the offer
+/// 
+/// 
+struct Terms {
+    expiry_date: Instant,
+    lines: Vec<Line>,
+    escrow: bool,
+    deposit_ratio: Decimal,
+    milestones: Vec<(String, Decimal, Instant)>,
+    quorum: Option<(Vec<NonFungibleLocalId>, u32, bool)>,
+    currency: ResourceAddress,
+}
+
+impl Terms {
+    fn new(offer_amount: Decimal) -> Terms {
+        Terms {
+            expiry_date: Instant::new(START_TIME + 30 * DAY),
+            lines: vec![line(offer_amount)],
+            escrow: false,
+            deposit_ratio: dec!(0),
+            milestones: vec![],
+            quorum: None,
+            currency: XRD,
+        }
+    }
+}
+
+/// The account of a Customer and the id of its Customer Badge
+/// 
+/// 
+struct Customer {
+    key: Secp256k1PublicKey,
+    account: ComponentAddress,
+    badge_id: NonFungibleLocalId,
+}
 
+/// The account of a Manager and the id of its Manager Badge
+/// 
+/// 
+struct Manager {
+    key: Secp256k1PublicKey,
+    account: ComponentAddress,
+    badge_id: NonFungibleLocalId,
+}
+
+/// A ledger with an instantiated component, accepting XRD, at START_TIME
+/// 
+/// 
+struct TestEnv {
+    ledger: DefaultLedgerSimulator,
+    component: ComponentAddress,
+    admin_key: Secp256k1PublicKey,
+    admin_account: ComponentAddress,
+    admin_badge: ResourceAddress,
+    manager_badge: ResourceAddress,
+    customer_badge: ResourceAddress,
+    nft_manager: ResourceAddress,
+    invoice_manager: ResourceAddress,
+    round: u64,
+}
+
+/// Instantiate the component with the badges in the Admin account, and accept XRD as a currency
+/// 
+/// 
+fn setup() -> TestEnv {
+    let mut ledger = LedgerSimulatorBuilder::new().without_kernel_trace().without_receipt_substate_check().build();
+    let (admin_key, _admin_private_key, admin_account) = ledger.new_allocated_account();
+    let package_address = ledger.compile_and_publish(this_package!());
+
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .call_function(package_address, "Fcgsales", "instantiate", ())
+        .try_deposit_entire_worktop_or_abort(admin_account, None)
+        .build();
+    let receipt = ledger.execute_manifest(manifest, vec![]);
+    let commit = receipt.expect_commit_success();
+    let resources = commit.new_resource_addresses();
 
+    let mut env = TestEnv {
+        component: commit.new_component_addresses()[0],
+        admin_key,
+        admin_account,
+        admin_badge: resources[1],
+        manager_badge: resources[2],
+        customer_badge: resources[3],
+        nft_manager: resources[4],
+        invoice_manager: resources[5],
+        round: 0,
+        ledger,
+    };
+    env.set_time(START_TIME);
+    env.call_as_admin("add_currency", (XRD,), env.admin_account).expect_commit_success();
+    env
+}
+
+impl TestEnv {
+    /// Move the clock of the ledger to the given time (UTC seconds)
+    fn set_time(&mut self, seconds: i64) {
+        self.round += 1;
+        self.ledger.advance_to_round_at_timestamp(Round::of(self.round), seconds * 1000).expect_commit_success();
+    }
+
+    /// Call a method of the component with the Admin badge, the returned resources are deposited to `deposit_to`
+    fn call_as_admin(&mut self, method: &str, arguments: impl ResolvableArguments, deposit_to: ComponentAddress) -> TransactionReceiptV1 {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_amount(self.admin_account, self.admin_badge, dec!(1))
+            .call_method(self.component, method, arguments)
+            .try_deposit_entire_worktop_or_abort(deposit_to, None)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![NonFungibleGlobalId::from_public_key(&self.admin_key)])
+    }
+
+    /// Call a method of the component with the Customer Badge, the returned resources are deposited to the customer account
+    fn call_as_customer(&mut self, customer: &Customer, method: &str, arguments: impl ResolvableArguments) -> TransactionReceiptV1 {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungible(customer.account, NonFungibleGlobalId::new(self.customer_badge, customer.badge_id.clone()))
+            .call_method(self.component, method, arguments)
+            .try_deposit_entire_worktop_or_abort(customer.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![NonFungibleGlobalId::from_public_key(&customer.key)])
+    }
+
+    /// Call a method of the component open to anyone, its output is the instruction #1 of the receipt
+    fn call_public(&mut self, method: &str, arguments: impl ResolvableArguments) -> TransactionReceiptV1 {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, method, arguments)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![])
+    }
+
+    /// Create an account holding a new Customer Badge
+    fn new_customer(&mut self, username: &str) -> Customer {
+        let (key, _private_key, account) = self.ledger.new_allocated_account();
+        let receipt = self.ledger.execute_manifest(
+            mint_customer_badge(self.component, self.admin_account, self.admin_badge, account, username.to_string()),
+            vec![NonFungibleGlobalId::from_public_key(&self.admin_key)],
+        );
+        receipt.expect_commit_success();
+        let badge_id = offer_ids(&mut self.ledger, account, self.customer_badge)[0].clone();
+        Customer { key, account, badge_id }
+    }
+
+    /// Send an offer to a Customer, returning the id of the offer
+    fn send_offer(&mut self, customer: &Customer, terms: Terms) -> NonFungibleLocalId {
+        self.try_send_offer(customer, terms).expect_commit_success();
+        self.last_offer(&customer.badge_id)
+    }
+
+    /// Send an offer to a Customer as an Admin, returning the receipt
+    fn try_send_offer(&mut self, customer: &Customer, terms: Terms) -> TransactionReceiptV1 {
+        self.call_as_admin(
+            "send_offer",
+            (
+                "hash_pdf".to_string(),
+                terms.expiry_date,
+                terms.lines,
+                terms.currency,
+                terms.escrow,
+                terms.deposit_ratio,
+                terms.milestones,
+                customer.badge_id.clone(),
+                terms.quorum,
+                None::<ManifestProof>,
+            ),
+            customer.account,
+        )
+    }
+
+    /// Get the id of the last offer sent to a Customer
+    fn last_offer(&mut self, customer_badge_id: &NonFungibleLocalId) -> NonFungibleLocalId {
+        let receipt = self.call_public("offers_by_customer", (customer_badge_id.clone(), None::<u64>, 100u32));
+        let (offers, _): (Vec<NonFungibleLocalId>, Option<u64>) = receipt.expect_commit_success().output(1);
+        offers.last().expect("No offers for this customer!").clone()
+    }
+
+    /// Get the data of an offer
+    fn offer(&mut self, offer_id: &NonFungibleLocalId) -> OfferData {
+        self.ledger.get_non_fungible_data::<OfferData>(self.nft_manager, offer_id.clone())
+    }
+
+    /// Get the number of OfferData NFTs held by the component (pending approval or cancelled)
+    fn held_offers(&mut self) -> Decimal {
+        self.ledger.get_component_resources(self.component).get(&self.nft_manager).cloned().unwrap_or_default()
+    }
+
+    /// Get the address of the vault of an account holding a resource
+    fn vault_of(&mut self, account: ComponentAddress, resource: ResourceAddress) -> InternalAddress {
+        let vault_id = self.ledger.get_component_vaults(account, resource)[0];
+        InternalAddress::new_or_panic(vault_id.0)
+    }
+
+    /// Call a payment method of the component with the Customer Badge and a payment in XRD, the change is deposited back
+    fn pay(&mut self, customer: &Customer, method: &str, offer_id: &NonFungibleLocalId, amount: Decimal) -> TransactionReceiptV1 {
+        let offer_id = offer_id.clone();
+        let badge_id = customer.badge_id.clone();
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungible(customer.account, NonFungibleGlobalId::new(self.customer_badge, badge_id.clone()))
+            .withdraw_from_account(customer.account, XRD, amount)
+            .take_all_from_worktop(XRD, "payment")
+            .call_method_with_name_lookup(self.component, method, |lookup| (offer_id, badge_id, lookup.bucket("payment")))
+            .try_deposit_entire_worktop_or_abort(customer.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![NonFungibleGlobalId::from_public_key(&customer.key)])
+    }
+
+    /// Get the XRD balance of an account
+    fn balance(&mut self, account: ComponentAddress) -> Decimal {
+        self.ledger.get_component_balance(account, XRD)
+    }
+
+    /// Pay a milestone of an offer with the Customer Badge and a payment in XRD, the change is deposited back
+    fn pay_milestone(&mut self, customer: &Customer, offer_id: &NonFungibleLocalId, index: u32, amount: Decimal) -> TransactionReceiptV1 {
+        let offer_id = offer_id.clone();
+        let badge_id = customer.badge_id.clone();
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungible(customer.account, NonFungibleGlobalId::new(self.customer_badge, badge_id.clone()))
+            .withdraw_from_account(customer.account, XRD, amount)
+            .take_all_from_worktop(XRD, "payment")
+            .call_method_with_name_lookup(self.component, "pay_milestone", |lookup| (offer_id, badge_id, index, lookup.bucket("payment")))
+            .try_deposit_entire_worktop_or_abort(customer.account, None)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![NonFungibleGlobalId::from_public_key(&customer.key)])
+    }
+
+    /// Create an account holding a new Manager Badge
+    fn new_manager(&mut self, username: &str) -> Manager {
+        let (key, _private_key, account) = self.ledger.new_allocated_account();
+        self.call_as_admin("mint_manager_badge", (username.to_string(),), account).expect_commit_success();
+        let badge_id = offer_ids(&mut self.ledger, account, self.manager_badge)[0].clone();
+        Manager { key, account, badge_id }
+    }
+
+    /// Call a method of the component with the Manager Badge, the arguments get a proof of the badge 
+    /// and the returned resources are deposited to `deposit_to`
+    fn call_as_manager<A: ResolvableArguments>(&mut self, manager: &Manager, method: &str, arguments: impl FnOnce(ManifestProof) -> A, deposit_to: ComponentAddress) -> TransactionReceiptV1 {
+        let global_id = NonFungibleGlobalId::new(self.manager_badge, manager.badge_id.clone());
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_non_fungible(manager.account, global_id.clone())
+            // a copy of the badge proof is passed to the component, the badge stays in the auth zone for the manager role
+            .create_proof_from_auth_zone_of_non_fungibles(global_id.resource_address(), [global_id.local_id().clone()], "manager_badge")
+            .call_method_with_name_lookup(self.component, method, |lookup| arguments(lookup.proof("manager_badge")))
+            .try_deposit_entire_worktop_or_abort(deposit_to, None)
+            .build();
+        self.ledger.execute_manifest(manifest, vec![NonFungibleGlobalId::from_public_key(&manager.key)])
+    }
+
+    /// Create an account holding a new Customer Badge of a member of an organisation, with the given role
+    fn new_member(&mut self, username: &str, organisation: u64, role: ManifestValue) -> Customer {
+        let (key, _private_key, account) = self.ledger.new_allocated_account();
+        self.call_as_admin("mint_customer_badge", (username.to_string(), Some(organisation), role, None::<ManifestProof>), account).expect_commit_success();
+        let badge_id = offer_ids(&mut self.ledger, account, self.customer_badge)[0].clone();
+        Customer { key, account, badge_id }
+    }
+}